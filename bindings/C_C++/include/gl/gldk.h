@@ -11,13 +11,32 @@ typedef enum glversion {
   V4_4,
   V4_5,
   V4_6,
+
+  ES2_0,
+  ES3_0,
+  ES3_1,
+  ES3_2,
 } GLVersion;
 
 typedef enum windowevent {
     RedrawRequested,
     Keydown,
     Keyup,
-    CloseRequested
+    CloseRequested,
+    CursorMoved,
+    CursorEntered,
+    CursorLeft,
+    MouseButtonDown,
+    MouseButtonUp,
+    MouseWheel,
+    Resized,
+    Moved,
+    Focused,
+    Minimized,
+    Restored,
+    ReceivedCharacter,
+    ImePreedit,
+    ImeCommit
 } WindowEvent;
 
 typedef struct glconfig {
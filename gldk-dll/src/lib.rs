@@ -20,7 +20,21 @@ pub enum WindowEvent {
     RedrawRequested,
     Keydown,
     Keyup,
-    CloseRequested
+    CloseRequested,
+    CursorMoved,
+    CursorEntered,
+    CursorLeft,
+    MouseButtonDown,
+    MouseButtonUp,
+    MouseWheel,
+    Resized,
+    Moved,
+    Focused,
+    Minimized,
+    Restored,
+    ReceivedCharacter,
+    ImePreedit,
+    ImeCommit
 }
 
 impl Into<WindowEvent> for gldk::window::WindowEvent {
@@ -30,6 +44,20 @@ impl Into<WindowEvent> for gldk::window::WindowEvent {
             gldk::window::WindowEvent::Keyup(_) => WindowEvent::Keyup,
             gldk::window::WindowEvent::Keydown(_) => WindowEvent::Keydown,
             gldk::window::WindowEvent::CloseRequested => WindowEvent::CloseRequested,
+            gldk::window::WindowEvent::CursorMoved { .. } => WindowEvent::CursorMoved,
+            gldk::window::WindowEvent::CursorEntered => WindowEvent::CursorEntered,
+            gldk::window::WindowEvent::CursorLeft => WindowEvent::CursorLeft,
+            gldk::window::WindowEvent::MouseButtonDown(_) => WindowEvent::MouseButtonDown,
+            gldk::window::WindowEvent::MouseButtonUp(_) => WindowEvent::MouseButtonUp,
+            gldk::window::WindowEvent::MouseWheel(_) => WindowEvent::MouseWheel,
+            gldk::window::WindowEvent::Resized(..) => WindowEvent::Resized,
+            gldk::window::WindowEvent::Moved(..) => WindowEvent::Moved,
+            gldk::window::WindowEvent::Focused(_) => WindowEvent::Focused,
+            gldk::window::WindowEvent::Minimized => WindowEvent::Minimized,
+            gldk::window::WindowEvent::Restored => WindowEvent::Restored,
+            gldk::window::WindowEvent::ReceivedCharacter(_) => WindowEvent::ReceivedCharacter,
+            gldk::window::WindowEvent::ImePreedit { .. } => WindowEvent::ImePreedit,
+            gldk::window::WindowEvent::ImeCommit(_) => WindowEvent::ImeCommit,
         }
     }
 }
@@ -81,6 +109,7 @@ pub extern "C" fn gldkCreateWindow(
 
     let config = gldk::GLConfig {
         version: config.version,
+        ..Default::default()
     };
 
     let b = Box::new(GLDKWindow::new(width, height, title, Some(config)).unwrap());
@@ -95,13 +124,13 @@ pub extern "C" fn gldkRunWindow(window: *mut GLDKWindow) {
 
     let window = unsafe { &*window };
 
-    window.run(|event| {
+    window.run(|event, _| {
         match event {
             gldk::window::WindowEvent::Keyup(c) => {
-                UPPED_KEY.lock().unwrap().set(c.0).unwrap()
+                UPPED_KEY.lock().unwrap().set(c.keycode as u32).unwrap()
             }
             gldk::window::WindowEvent::Keydown(c) => {
-                DOWNED_KEY.lock().unwrap().set(c.0).unwrap()
+                DOWNED_KEY.lock().unwrap().set(c.keycode as u32).unwrap()
             }
             gldk::window::WindowEvent::RedrawRequested => {
                 match REDRAW_REQUESTED.lock().unwrap().get() {
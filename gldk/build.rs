@@ -30,6 +30,7 @@ fn main() {
                 "WGL_ARB_extensions_string",
                 "WGL_ARB_framebuffer_sRGB",
                 "WGL_ARB_multisample",
+                "WGL_ARB_pbuffer",
                 "WGL_ARB_pixel_format",
                 "WGL_ARB_pixel_format_float",
                 "WGL_EXT_create_context_es2_profile",
@@ -41,4 +42,11 @@ fn main() {
         .write_bindings(gl_generator::StructGenerator, &mut file)
         .unwrap();
     }
+
+    if target.contains("linux") {
+        let mut file = File::create(dest.join("egl_bindings.rs")).unwrap();
+        Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [])
+            .write_bindings(gl_generator::StaticGenerator, &mut file)
+            .unwrap();
+    }
 }
@@ -0,0 +1,42 @@
+use gldk::headless::GLDKHeadlessContext;
+use gldk::{GLConfig, GLVersion};
+
+fn main() {
+    let context = GLDKHeadlessContext::new(
+        256,
+        256,
+        Some(GLConfig {
+            version: GLVersion::V3_3,
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    context.make_current();
+
+    gl::load_with(|s| context.get_proc_address(s));
+
+    let info = context.get_context_info();
+    println!("Renderer: {}", info.renderer);
+    println!("Version: {}", info.version_string);
+
+    let (width, height) = context.get_framebuffer_size();
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+    unsafe {
+        gl::Viewport(0, 0, width as i32, height as i32);
+        gl::ClearColor(0.2, 0.4, 0.6, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+    }
+
+    println!("Pixel at (0, 0): {:?}", &pixels[..4]);
+}
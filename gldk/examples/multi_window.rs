@@ -0,0 +1,46 @@
+use gldk::event_loop::GLDKEventLoop;
+use gldk::window::{ControlFlow, WindowEvent, WindowID};
+use std::collections::HashMap;
+
+fn main() {
+    let event_loop = GLDKEventLoop::new().unwrap();
+
+    // Every window gets its own clear color, keyed by its ID.
+    let mut colors: HashMap<WindowID, [f32; 3]> = HashMap::new();
+    for (title, color) in [("GLDK window 1", [0.8, 0.2, 0.2]), ("GLDK window 2", [0.2, 0.2, 0.8])] {
+        let window = event_loop.create_window(400, 300, title, None).unwrap();
+        window.show();
+        colors.insert(window.id(), color);
+    }
+
+    event_loop.run(|id, event, control_flow| match event {
+        WindowEvent::RedrawRequested => {
+            let window = event_loop.get_window(id).unwrap();
+            let [r, g, b] = colors[&id];
+
+            window.make_current();
+            gl::load_with(|s| window.get_proc_address(s));
+            unsafe {
+                gl::ClearColor(r, g, b, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+            window.swap_buffers();
+        }
+        // Any key opens another window.
+        WindowEvent::Keydown(_) => {
+            let window = event_loop
+                .create_window(400, 300, "GLDK window", None)
+                .unwrap();
+            window.show();
+            colors.insert(window.id(), [0.2, 0.8, 0.2]);
+        }
+        WindowEvent::CloseRequested => {
+            event_loop.destroy_window(id);
+            colors.remove(&id);
+            if event_loop.window_ids().is_empty() {
+                *control_flow = ControlFlow::Exit(0);
+            }
+        }
+        _ => {}
+    });
+}
@@ -1,11 +1,10 @@
 use gl::types::*;
-use gldk::window::{GLDKWindow, KeyCode, WindowEvent};
+use gldk::window::{ControlFlow, GLDKWindow, WindowEvent};
 use gldk::{GLConfig, GLVersion};
 use glm::Vector3;
-use std::ffi::{c_char, c_void, CStr, CString};
+use std::ffi::{c_void, CString};
 use std::mem::size_of;
 use std::ptr;
-use winapi::um::gl::gl::{GLenum, GLint, GLuint};
 
 static VS_SRC: &'static str = "
 #version 400
@@ -38,6 +37,7 @@ fn main() {
         "GLDK example",
         Some(GLConfig {
             version: GLVersion::V3_3,
+            ..Default::default()
         }),
     ).unwrap();
 
@@ -46,21 +46,12 @@ fn main() {
 
     gl::load_with(|s| window.get_proc_address(s));
 
+    let info = window.get_context_info();
+    println!("{} {} {}", info.version_string, info.vendor, info.renderer);
+
     unsafe {
         gl::DepthFunc(gl::LEQUAL);
         gl::Enable(gl::DEPTH_TEST);
-        println!(
-            "{} {} {}",
-            CStr::from_ptr(gl::GetString(gl::VERSION) as *const c_char)
-                .to_str()
-                .unwrap(),
-            CStr::from_ptr(gl::GetString(gl::VENDOR) as *const c_char)
-                .to_str()
-                .unwrap(),
-            CStr::from_ptr(gl::GetString(gl::RENDERER) as *const c_char)
-                .to_str()
-                .unwrap()
-        )
     }
 
     let vs = compile_shader(VS_SRC, gl::VERTEX_SHADER);
@@ -91,7 +82,7 @@ fn main() {
 
     window.show();
 
-    window.run(|event| match event {
+    window.run(|event, control_flow| match event {
         WindowEvent::RedrawRequested => {
             unsafe {
                 gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
@@ -102,8 +93,12 @@ fn main() {
             window.swap_buffers();
         }
 
+        WindowEvent::Resized(width, height) => unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        },
+
         WindowEvent::CloseRequested => {
-            window.quit();
+            *control_flow = ControlFlow::Exit(0);
         }
 
         _ => {}
@@ -0,0 +1,188 @@
+use crate::{GLContextFlags, GLProfile, GLResetNotification, GLVersion};
+use gl::types::{GLenum, GLint, GLubyte, GLuint};
+use std::ffi::{c_void, CStr};
+use std::mem::transmute;
+
+// GL 4.6 / KHR_no_error, not part of the gl crate's 4.5 bindings
+const CONTEXT_FLAG_NO_ERROR_BIT: GLenum = 0x00000008;
+
+#[allow(clippy::upper_case_acronyms)]
+type GLGETSTRINGPROC = unsafe extern "system" fn(name: GLenum) -> *const GLubyte;
+#[allow(clippy::upper_case_acronyms)]
+type GLGETSTRINGIPROC = unsafe extern "system" fn(name: GLenum, index: GLuint) -> *const GLubyte;
+#[allow(clippy::upper_case_acronyms)]
+type GLGETINTEGERVPROC = unsafe extern "system" fn(pname: GLenum, data: *mut GLint);
+#[allow(clippy::upper_case_acronyms)]
+type GLGETERRORPROC = unsafe extern "system" fn() -> GLenum;
+
+/// What the driver actually created, collected when the window is created.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextInfo {
+    /// The version the driver reports, which may be above the one requested. Versions newer than
+    /// GLDK knows are reported as the newest it does, `version_string` has the exact one.
+    pub version: GLVersion,
+    /// `None` for contexts without profiles (OpenGL ES and OpenGL below 3.2).
+    pub profile: Option<GLProfile>,
+    pub flags: GLContextFlags,
+    pub vendor: String,
+    pub renderer: String,
+    /// The raw `GL_VERSION` string, which usually carries driver details.
+    pub version_string: String,
+    pub shading_language_version: String,
+    pub extensions: Vec<String>,
+}
+
+impl ContextInfo {
+    /// Queries the context that is current on the calling thread. `requested` is only reported if
+    /// the driver's version can't be read.
+    pub(crate) fn query<F>(requested: GLVersion, get_proc_address: F) -> Self
+    where
+        F: Fn(&str) -> *const c_void,
+    {
+        unsafe {
+            let get_string: Option<GLGETSTRINGPROC> = transmute(get_proc_address("glGetString"));
+            let get_stringi: Option<GLGETSTRINGIPROC> = transmute(get_proc_address("glGetStringi"));
+            let get_integerv: Option<GLGETINTEGERVPROC> = transmute(get_proc_address("glGetIntegerv"));
+            let get_error: Option<GLGETERRORPROC> = transmute(get_proc_address("glGetError"));
+
+            let string = |name: GLenum| match get_string {
+                Some(f) => to_string(f(name)),
+                None => String::new(),
+            };
+            let integer = |name: GLenum| {
+                let mut value = 0;
+                if let Some(f) = get_integerv {
+                    f(name, &mut value);
+                }
+                value as GLenum
+            };
+
+            let version_string = string(gl::VERSION);
+            let major = integer(gl::MAJOR_VERSION);
+            let minor = integer(gl::MINOR_VERSION);
+            let version = actual_version(&version_string, major, minor).unwrap_or(requested);
+
+            let profile = if !version.is_es() && version.major_minor() >= (3, 2) {
+                let mask = integer(gl::CONTEXT_PROFILE_MASK);
+                if mask & gl::CONTEXT_CORE_PROFILE_BIT != 0 {
+                    Some(GLProfile::Core)
+                } else if mask & gl::CONTEXT_COMPATIBILITY_PROFILE_BIT != 0 {
+                    Some(GLProfile::Compatibility)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            // OpenGL ES only has context flags from 3.2.
+            let has_flags = if version.is_es() {
+                version.major_minor() >= (3, 2)
+            } else {
+                version.major_minor() >= (3, 0)
+            };
+            let context_flags = if has_flags {
+                integer(gl::CONTEXT_FLAGS)
+            } else {
+                0
+            };
+            let robust_access = if context_flags & gl::CONTEXT_FLAG_ROBUST_ACCESS_BIT != 0 {
+                match integer(gl::RESET_NOTIFICATION_STRATEGY) {
+                    gl::LOSE_CONTEXT_ON_RESET => Some(GLResetNotification::LoseContextOnReset),
+                    _ => Some(GLResetNotification::NoResetNotification),
+                }
+            } else {
+                None
+            };
+            let flags = GLContextFlags {
+                debug: context_flags & gl::CONTEXT_FLAG_DEBUG_BIT != 0,
+                forward_compatible: context_flags & gl::CONTEXT_FLAG_FORWARD_COMPATIBLE_BIT != 0,
+                robust_access,
+                no_error: context_flags & CONTEXT_FLAG_NO_ERROR_BIT != 0,
+            };
+
+            // GL_EXTENSIONS can't be passed to glGetString on core profiles.
+            let extensions = match get_stringi {
+                Some(f) if version.major_minor() >= (3, 0) => (0..integer(gl::NUM_EXTENSIONS))
+                    .map(|i| to_string(f(gl::EXTENSIONS, i)))
+                    .collect(),
+                _ => string(gl::EXTENSIONS)
+                    .split_whitespace()
+                    .map(|e| e.to_owned())
+                    .collect(),
+            };
+
+            let info = Self {
+                version,
+                profile,
+                flags,
+                vendor: string(gl::VENDOR),
+                renderer: string(gl::RENDERER),
+                version_string,
+                shading_language_version: string(gl::SHADING_LANGUAGE_VERSION),
+                extensions,
+            };
+
+            // Don't leave errors from queries the context doesn't know about to the application.
+            if let Some(f) = get_error {
+                for _ in 0..16 {
+                    if f() == gl::NO_ERROR {
+                        break;
+                    }
+                }
+            }
+
+            info
+        }
+    }
+}
+
+/// `GL_MAJOR_VERSION` and `GL_MINOR_VERSION` are 0 on contexts that don't know them, OpenGL ES 2.0
+/// among them, which leaves parsing `GL_VERSION`: "<major>.<minor>[.<release>] <vendor details>",
+/// prefixed with "OpenGL ES " on OpenGL ES.
+fn actual_version(version_string: &str, major: GLenum, minor: GLenum) -> Option<GLVersion> {
+    let es = version_string.starts_with("OpenGL ES");
+    if major != 0 {
+        return GLVersion::from_major_minor(es, major, minor);
+    }
+    let number = version_string
+        .strip_prefix("OpenGL ES ")
+        .unwrap_or(version_string)
+        .split_whitespace()
+        .next()?;
+    let mut parts = number.split('.').map(|part| part.parse::<u32>().ok());
+    let (major, minor) = (parts.next()??, parts.next()??);
+    GLVersion::from_major_minor(es, major, minor)
+}
+
+unsafe fn to_string(s: *const GLubyte) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(s as *const _).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::actual_version;
+    use crate::GLVersion;
+
+    #[test]
+    fn actual_version_prefers_the_integers() {
+        assert_eq!(actual_version("4.5 (Core Profile) Mesa 23.2.1", 4, 5), Some(GLVersion::V4_5));
+        assert_eq!(actual_version("OpenGL ES 3.2 Mesa 23.2.1", 3, 2), Some(GLVersion::ES3_2));
+    }
+
+    #[test]
+    fn actual_version_parses_the_string() {
+        assert_eq!(actual_version("OpenGL ES 2.0 Mesa 23.2.1", 0, 0), Some(GLVersion::ES2_0));
+        assert_eq!(actual_version("3.3.0 NVIDIA 535.104.05", 0, 0), Some(GLVersion::V3_3));
+        assert_eq!(actual_version("garbage", 0, 0), None);
+    }
+
+    #[test]
+    fn actual_version_maps_unknown_versions_down() {
+        assert_eq!(actual_version("4.7.0", 4, 7), Some(GLVersion::V4_6));
+        assert_eq!(actual_version("2.1 Mesa 23.2.1", 2, 1), None);
+    }
+}
@@ -1,15 +1,28 @@
 use std::{error, fmt};
 use crate::error::_GLDKError::{Custom, Simple};
+use crate::GLVersion;
 
 #[derive(Clone)]
 pub enum ErrorKind {
-    Unexpected(String)
+    Unexpected(String),
+    UnsupportedVersion(GLVersion),
+    UnsupportedVersionRange(GLVersion, GLVersion),
+    InvalidConfig(String),
+    Unsupported(String),
 }
 
 impl ErrorKind {
     pub fn description(&self) -> String {
         match self {
-            ErrorKind::Unexpected(detail) => "Unexpected error! Detailed description: ".to_owned() + detail
+            ErrorKind::Unexpected(detail) => "Unexpected error! Detailed description: ".to_owned() + detail,
+            ErrorKind::UnsupportedVersion(version) => {
+                format!("{} context is not supported by the driver.", version)
+            }
+            ErrorKind::UnsupportedVersionRange(highest, lowest) => {
+                format!("None of {} down to {} is supported by the driver.", highest, lowest)
+            }
+            ErrorKind::InvalidConfig(detail) => "Invalid GLConfig: ".to_owned() + detail,
+            ErrorKind::Unsupported(detail) => "Not supported by the driver: ".to_owned() + detail,
         }
     }
 }
@@ -19,7 +32,7 @@ pub struct GLDKError {
 }
 
 impl GLDKError {
-    fn kind(&self) -> ErrorKind {
+    pub fn kind(&self) -> ErrorKind {
         match &self._error {
             Simple(s) => s.clone(),
             Custom(c) => c.0.clone(),
@@ -39,6 +52,10 @@ impl GLDKError {
     pub(crate) fn new_unexpected(desc: String) -> Self {
         Self { _error: _GLDKError::Simple(ErrorKind::Unexpected(desc)) }
     }
+
+    pub(crate) fn new_simple(kind: ErrorKind) -> Self {
+        Self { _error: _GLDKError::Simple(kind) }
+    }
 }
 
 impl fmt::Debug for GLDKError {
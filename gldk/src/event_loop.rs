@@ -0,0 +1,126 @@
+use crate::error::GLDKError;
+use crate::platform_impl::window::REventLoop;
+use crate::window::{ControlFlow, GLDKWindow, WindowEvent, WindowID};
+use crate::GLConfig;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Drives the events of several windows from one loop, tagging each event with the window it belongs to.
+///
+/// Windows can be created and destroyed at any time, including from within `run`'s callback.
+#[repr(C)]
+pub struct GLDKEventLoop {
+    inner: REventLoop,
+    windows: RefCell<HashMap<WindowID, Rc<GLDKWindow>>>,
+    running: Cell<bool>,
+}
+
+impl GLDKEventLoop {
+    pub fn new() -> Result<Self, GLDKError> {
+        Ok(Self {
+            inner: REventLoop::new()?,
+            windows: RefCell::new(HashMap::new()),
+            running: Cell::new(false),
+        })
+    }
+
+    pub fn create_window(
+        &self,
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: Option<GLConfig>,
+    ) -> Result<Rc<GLDKWindow>, GLDKError> {
+        self.create(width, height, title, conf, None)
+    }
+
+    /// Creates a window whose context shares textures, buffers and other objects with `share`'s context.
+    /// `share` has to belong to this event loop.
+    pub fn create_shared_window(
+        &self,
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: Option<GLConfig>,
+        share: &GLDKWindow,
+    ) -> Result<Rc<GLDKWindow>, GLDKError> {
+        self.create(width, height, title, conf, Some(share))
+    }
+
+    fn create(
+        &self,
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: Option<GLConfig>,
+        share: Option<&GLDKWindow>,
+    ) -> Result<Rc<GLDKWindow>, GLDKError> {
+        let conf = conf.unwrap_or_default();
+
+        conf.validate()?;
+
+        let inner = self
+            .inner
+            .create_window(width, height, title, conf, share.map(|s| &s.inner))?;
+        let window = Rc::new(GLDKWindow { inner });
+        self.windows.borrow_mut().insert(window.id(), window.clone());
+        Ok(window)
+    }
+
+    pub fn get_window(&self, id: WindowID) -> Option<Rc<GLDKWindow>> {
+        self.windows.borrow().get(&id).cloned()
+    }
+
+    pub fn window_ids(&self) -> Vec<WindowID> {
+        self.windows.borrow().keys().copied().collect()
+    }
+
+    /// Stops delivering events to the window. It is closed once the last `Rc` to it is dropped.
+    pub fn destroy_window(&self, id: WindowID) {
+        let window = self.windows.borrow_mut().remove(&id);
+        drop(window);
+    }
+
+    /// Passes every event of the loop's windows to `callback` until it sets `ControlFlow::Exit`,
+    /// returning its code, or until `quit` is called, returning 0. Returns 1 if the connection to the
+    /// display server is lost.
+    pub fn run<F>(&self, mut callback: F) -> i32
+    where
+        F: FnMut(WindowID, WindowEvent, &mut ControlFlow),
+    {
+        let mut control_flow = ControlFlow::default();
+        self.running.set(true);
+        loop {
+            if let ControlFlow::Exit(code) = control_flow {
+                return code;
+            }
+            if !self.running.get() {
+                return 0;
+            }
+            match self.inner.wait_event(control_flow.timeout()) {
+                Ok(Some((id, event))) => {
+                    let known = self.windows.borrow().contains_key(&id);
+                    if known {
+                        callback(id, event, &mut control_flow);
+                    }
+                }
+                Ok(None) if control_flow.is_due() => {
+                    // Windows may be destroyed along the way.
+                    for id in self.window_ids() {
+                        let known = self.windows.borrow().contains_key(&id);
+                        if known {
+                            callback(id, WindowEvent::RedrawRequested, &mut control_flow);
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(_) => return 1,
+            }
+        }
+    }
+
+    pub fn quit(&self) {
+        self.running.set(false);
+    }
+}
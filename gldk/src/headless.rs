@@ -0,0 +1,53 @@
+use crate::context::ContextInfo;
+use crate::error::GLDKError;
+use crate::platform_impl::headless::RHeadlessContext;
+use crate::{GLConfig, GLFramebufferFormat, GLVersion};
+use std::ffi::c_void;
+
+/// An OpenGL context rendering into an offscreen framebuffer, without any visible window.
+///
+/// Uses a pbuffer on EGL's surfaceless platform on Linux, so no display server is needed,
+/// and a `WGL_ARB_pbuffer` pbuffer on Windows.
+#[repr(C)]
+pub struct GLDKHeadlessContext {
+    inner: RHeadlessContext,
+}
+
+impl GLDKHeadlessContext {
+    pub fn new(width: u32, height: u32, conf: Option<GLConfig>) -> Result<Self, GLDKError> {
+        let conf = conf.unwrap_or_default();
+
+        conf.validate()?;
+
+        Ok(Self {
+            inner: RHeadlessContext::new(width, height, conf)?,
+        })
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const c_void {
+        self.inner.get_proc_address(addr)
+    }
+
+    /// Returns the version that was actually created, see `GLConfig::version_policy`.
+    pub fn get_gl_version(&self) -> GLVersion {
+        self.inner.get_gl_version()
+    }
+
+    pub fn get_context_info(&self) -> &ContextInfo {
+        self.inner.get_context_info()
+    }
+
+    /// Returns the format of the offscreen framebuffer. It is always single-buffered.
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        self.inner.get_framebuffer_format()
+    }
+
+    /// Returns the size of the default framebuffer, which is fixed at creation.
+    pub fn get_framebuffer_size(&self) -> (u32, u32) {
+        self.inner.get_framebuffer_size()
+    }
+
+    pub fn make_current(&self) {
+        self.inner.make_current();
+    }
+}
@@ -1,7 +1,13 @@
+use crate::error::{ErrorKind, GLDKError};
+use std::fmt;
+
 mod platform_impl;
 mod sys;
 pub mod window;
+pub mod event_loop;
+pub mod headless;
 pub mod error;
+pub mod context;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -18,17 +24,433 @@ pub enum GLVersion {
     V4_4,
     V4_5,
     V4_6,
+
+    ES2_0,
+    ES3_0,
+    ES3_1,
+    ES3_2,
+}
+
+const DESKTOP_VERSIONS: [GLVersion; 11] = [
+    GLVersion::V3_0,
+    GLVersion::V3_1,
+    GLVersion::V3_2,
+    GLVersion::V3_3,
+    GLVersion::V4_0,
+    GLVersion::V4_1,
+    GLVersion::V4_2,
+    GLVersion::V4_3,
+    GLVersion::V4_4,
+    GLVersion::V4_5,
+    GLVersion::V4_6,
+];
+
+const ES_VERSIONS: [GLVersion; 4] = [
+    GLVersion::ES2_0,
+    GLVersion::ES3_0,
+    GLVersion::ES3_1,
+    GLVersion::ES3_2,
+];
+
+impl GLVersion {
+    pub(crate) fn major_minor(&self) -> (u32, u32) {
+        match self {
+            GLVersion::V3_0 => (3, 0),
+            GLVersion::V3_1 => (3, 1),
+            GLVersion::V3_2 => (3, 2),
+            GLVersion::V3_3 => (3, 3),
+            GLVersion::V4_0 => (4, 0),
+            GLVersion::V4_1 => (4, 1),
+            GLVersion::V4_2 => (4, 2),
+            GLVersion::V4_3 => (4, 3),
+            GLVersion::V4_4 => (4, 4),
+            GLVersion::V4_5 => (4, 5),
+            GLVersion::V4_6 => (4, 6),
+            GLVersion::ES2_0 => (2, 0),
+            GLVersion::ES3_0 => (3, 0),
+            GLVersion::ES3_1 => (3, 1),
+            GLVersion::ES3_2 => (3, 2),
+        }
+    }
+
+    pub fn is_es(&self) -> bool {
+        matches!(
+            self,
+            GLVersion::ES2_0 | GLVersion::ES3_0 | GLVersion::ES3_1 | GLVersion::ES3_2
+        )
+    }
+
+    /// The highest known version that isn't above `major.minor`, so versions newer than GLDK
+    /// still map to something. `None` below `V3_0` or `ES2_0`.
+    pub(crate) fn from_major_minor(es: bool, major: u32, minor: u32) -> Option<GLVersion> {
+        let versions = if es { &ES_VERSIONS[..] } else { &DESKTOP_VERSIONS[..] };
+        versions
+            .iter()
+            .rev()
+            .find(|v| v.major_minor() <= (major, minor))
+            .copied()
+    }
+}
+
+impl fmt::Display for GLVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (major, minor) = self.major_minor();
+        if self.is_es() {
+            write!(f, "OpenGL ES {}.{}", major, minor)
+        } else {
+            write!(f, "OpenGL {}.{}", major, minor)
+        }
+    }
+}
+
+/// How strictly `GLConfig::version` has to be honoured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GLVersionPolicy {
+    /// Fail unless exactly `GLConfig::version` can be created.
+    Exact,
+    /// Try `GLConfig::version` first, then every lower version down to the given one.
+    /// Use `GLVersion::V4_6` (or `GLVersion::ES3_2`) as `GLConfig::version` to get the highest available.
+    DownTo(GLVersion),
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GLProfile {
+    Core,
+    Compatibility,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GLResetNotification {
+    NoResetNotification,
+    LoseContextOnReset,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GLContextFlags {
+    pub debug: bool,
+    pub forward_compatible: bool,
+    /// Requests a robust access context with the given reset notification strategy.
+    pub robust_access: Option<GLResetNotification>,
+    /// Can't be combined with `debug` or `robust_access`.
+    pub no_error: bool,
+}
+
+/// Bit depths of the default framebuffer.
+/// Used both to request a format in `GLConfig` and to report the format actually obtained.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GLFramebufferFormat {
+    pub red_bits: u8,
+    pub green_bits: u8,
+    pub blue_bits: u8,
+    pub alpha_bits: u8,
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+    pub double_buffer: bool,
+    /// Number of MSAA samples. 0 means a single-sampled framebuffer.
+    pub samples: u8,
+    /// Requests an sRGB-capable framebuffer (enable it with `GL_FRAMEBUFFER_SRGB`).
+    pub srgb: bool,
+}
+
+impl Default for GLFramebufferFormat {
+    fn default() -> Self {
+        Self {
+            red_bits: 8,
+            green_bits: 8,
+            blue_bits: 8,
+            alpha_bits: 8,
+            depth_bits: 24,
+            stencil_bits: 8,
+            double_buffer: true,
+            samples: 0,
+            srgb: false,
+        }
+    }
+}
+
+/// The platform interface used to create contexts.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GLContextBackend {
+    /// GLX on X11, EGL on Wayland, WGL on Windows.
+    #[default]
+    Native,
+    /// EGL, for EGL-only drivers. Only available on Linux.
+    /// Headless contexts and Wayland windows always use EGL.
+    Egl,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct GLConfig {
+    pub backend: GLContextBackend,
     pub version: GLVersion,
+    pub version_policy: GLVersionPolicy,
+    /// `None` leaves the choice to GLDK (core profile from OpenGL 3.2).
+    /// Profiles don't exist before OpenGL 3.2 nor on OpenGL ES, so `Some` requires at least `GLVersion::V3_2`.
+    pub profile: Option<GLProfile>,
+    pub flags: GLContextFlags,
+    pub framebuffer: GLFramebufferFormat,
+}
+
+impl GLConfig {
+    pub(crate) fn validate(&self) -> Result<(), GLDKError> {
+        if self.profile.is_some() && self.version.is_es() {
+            return Err(GLDKError::new_simple(ErrorKind::InvalidConfig(
+                "OpenGL ES has no profiles.".to_owned(),
+            )));
+        }
+        if self.profile.is_some() && self.version.major_minor() < (3, 2) {
+            return Err(GLDKError::new_simple(ErrorKind::InvalidConfig(
+                "OpenGL profiles are only available from OpenGL 3.2.".to_owned(),
+            )));
+        }
+        if let GLVersionPolicy::DownTo(lowest) = self.version_policy {
+            if lowest.is_es() != self.version.is_es() || lowest.major_minor() > self.version.major_minor() {
+                return Err(GLDKError::new_simple(ErrorKind::InvalidConfig(
+                    "The fallback version must be of the same API and not above version.".to_owned(),
+                )));
+            }
+            if self.profile.is_some() && lowest.major_minor() < (3, 2) {
+                return Err(GLDKError::new_simple(ErrorKind::InvalidConfig(
+                    "OpenGL profiles are only available from OpenGL 3.2.".to_owned(),
+                )));
+            }
+        }
+        if self.flags.no_error && (self.flags.debug || self.flags.robust_access.is_some()) {
+            return Err(GLDKError::new_simple(ErrorKind::InvalidConfig(
+                "no_error can't be combined with debug or robust_access.".to_owned(),
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn profile(&self) -> GLProfile {
+        self.profile.unwrap_or(GLProfile::Core)
+    }
+
+    /// Calls `create` with every version allowed by `version_policy`, highest first,
+    /// and returns the first context that could be created along with its version.
+    pub(crate) fn negotiate<T, F>(&self, mut create: F) -> Result<(T, GLVersion), GLDKError>
+    where
+        F: FnMut(&GLConfig) -> Result<T, GLDKError>,
+    {
+        let lowest = match self.version_policy {
+            GLVersionPolicy::Exact => self.version,
+            GLVersionPolicy::DownTo(lowest) => lowest,
+        };
+        let versions = if self.version.is_es() {
+            &ES_VERSIONS[..]
+        } else {
+            &DESKTOP_VERSIONS[..]
+        };
+
+        for version in versions.iter().rev().filter(|v| {
+            v.major_minor() <= self.version.major_minor() && v.major_minor() >= lowest.major_minor()
+        }) {
+            let conf = GLConfig {
+                version: *version,
+                ..*self
+            };
+            match create(&conf) {
+                Ok(ctx) => return Ok((ctx, *version)),
+                Err(e) => match e.kind() {
+                    ErrorKind::UnsupportedVersion(_) => continue,
+                    _ => return Err(e),
+                },
+            }
+        }
+
+        if lowest == self.version {
+            Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(self.version)))
+        } else {
+            Err(GLDKError::new_simple(ErrorKind::UnsupportedVersionRange(
+                self.version,
+                lowest,
+            )))
+        }
+    }
 }
 
 impl Default for GLConfig {
     fn default() -> Self {
         Self {
+            backend: GLContextBackend::Native,
             version: GLVersion::V3_1,
+            version_policy: GLVersionPolicy::Exact,
+            profile: None,
+            flags: GLContextFlags::default(),
+            framebuffer: GLFramebufferFormat::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(version: GLVersion, version_policy: GLVersionPolicy, profile: Option<GLProfile>) -> GLConfig {
+        GLConfig {
+            version,
+            version_policy,
+            profile,
+            ..Default::default()
         }
     }
+
+    fn is_invalid(conf: &GLConfig) -> bool {
+        matches!(conf.validate().map_err(|e| e.kind()), Err(ErrorKind::InvalidConfig(_)))
+    }
+
+    /// The versions `negotiate` tries, in order, when the driver supports none of them.
+    fn candidates(conf: &GLConfig) -> Vec<GLVersion> {
+        let mut tried = Vec::new();
+        let _ = conf.negotiate(|conf| -> Result<(), GLDKError> {
+            tried.push(conf.version);
+            Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)))
+        });
+        tried
+    }
+
+    #[test]
+    fn validate_accepts_default() {
+        assert!(GLConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_profiles() {
+        use GLVersionPolicy::Exact;
+
+        assert!(config(GLVersion::V3_2, Exact, Some(GLProfile::Core)).validate().is_ok());
+        assert!(config(GLVersion::V4_6, Exact, Some(GLProfile::Compatibility)).validate().is_ok());
+        assert!(config(GLVersion::V3_1, Exact, None).validate().is_ok());
+        assert!(config(GLVersion::ES3_2, Exact, None).validate().is_ok());
+
+        assert!(is_invalid(&config(GLVersion::V3_1, Exact, Some(GLProfile::Core))));
+        assert!(is_invalid(&config(GLVersion::V3_0, Exact, Some(GLProfile::Compatibility))));
+        assert!(is_invalid(&config(GLVersion::ES3_2, Exact, Some(GLProfile::Core))));
+        assert!(is_invalid(&config(GLVersion::ES2_0, Exact, Some(GLProfile::Compatibility))));
+    }
+
+    #[test]
+    fn validate_fallback_ranges() {
+        use GLVersionPolicy::DownTo;
+
+        assert!(config(GLVersion::V4_6, DownTo(GLVersion::V3_0), None).validate().is_ok());
+        assert!(config(GLVersion::V4_6, DownTo(GLVersion::V3_2), Some(GLProfile::Core)).validate().is_ok());
+        assert!(config(GLVersion::ES3_2, DownTo(GLVersion::ES2_0), None).validate().is_ok());
+        assert!(config(GLVersion::V3_3, DownTo(GLVersion::V3_3), None).validate().is_ok());
+
+        // A range crossing 3.2 can't have a profile, its lower end has none.
+        assert!(is_invalid(&config(GLVersion::V4_6, DownTo(GLVersion::V3_1), Some(GLProfile::Core))));
+        // Both ends have to be the same API.
+        assert!(is_invalid(&config(GLVersion::V4_6, DownTo(GLVersion::ES3_0), None)));
+        assert!(is_invalid(&config(GLVersion::ES3_2, DownTo(GLVersion::V3_0), None)));
+        // The lower end can't be above the requested version.
+        assert!(is_invalid(&config(GLVersion::V3_3, DownTo(GLVersion::V4_0), None)));
+        assert!(is_invalid(&config(GLVersion::ES2_0, DownTo(GLVersion::ES3_0), None)));
+    }
+
+    #[test]
+    fn validate_no_error_flag() {
+        let with_flags = |flags| GLConfig {
+            flags,
+            ..Default::default()
+        };
+
+        assert!(with_flags(GLContextFlags {
+            no_error: true,
+            forward_compatible: true,
+            ..Default::default()
+        })
+        .validate()
+        .is_ok());
+
+        assert!(is_invalid(&with_flags(GLContextFlags {
+            no_error: true,
+            debug: true,
+            ..Default::default()
+        })));
+        assert!(is_invalid(&with_flags(GLContextFlags {
+            no_error: true,
+            robust_access: Some(GLResetNotification::LoseContextOnReset),
+            ..Default::default()
+        })));
+    }
+
+    #[test]
+    fn negotiate_exact_tries_only_the_requested_version() {
+        let conf = config(GLVersion::V3_3, GLVersionPolicy::Exact, None);
+        assert_eq!(candidates(&conf), [GLVersion::V3_3]);
+
+        let error = conf
+            .negotiate(|conf| -> Result<(), GLDKError> {
+                Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)))
+            })
+            .unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::UnsupportedVersion(GLVersion::V3_3)));
+    }
+
+    #[test]
+    fn negotiate_tries_highest_first() {
+        use GLVersion::*;
+
+        let conf = config(V4_6, GLVersionPolicy::DownTo(V3_0), None);
+        assert_eq!(
+            candidates(&conf),
+            [V4_6, V4_5, V4_4, V4_3, V4_2, V4_1, V4_0, V3_3, V3_2, V3_1, V3_0]
+        );
+
+        let conf = config(V4_1, GLVersionPolicy::DownTo(V3_2), Some(GLProfile::Core));
+        assert_eq!(candidates(&conf), [V4_1, V4_0, V3_3, V3_2]);
+
+        let conf = config(ES3_2, GLVersionPolicy::DownTo(ES2_0), None);
+        assert_eq!(candidates(&conf), [ES3_2, ES3_1, ES3_0, ES2_0]);
+    }
+
+    #[test]
+    fn negotiate_reports_the_range_when_nothing_works() {
+        let conf = config(GLVersion::V4_6, GLVersionPolicy::DownTo(GLVersion::V4_0), None);
+        let error = conf
+            .negotiate(|conf| -> Result<(), GLDKError> {
+                Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)))
+            })
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedVersionRange(GLVersion::V4_6, GLVersion::V4_0)
+        ));
+    }
+
+    #[test]
+    fn negotiate_returns_the_first_version_created() {
+        let conf = config(GLVersion::V4_6, GLVersionPolicy::DownTo(GLVersion::V3_0), None);
+        let (ctx, version) = conf
+            .negotiate(|conf| {
+                if conf.version.major_minor() <= (4, 3) {
+                    Ok(conf.version)
+                } else {
+                    Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)))
+                }
+            })
+            .unwrap();
+        assert_eq!(version, GLVersion::V4_3);
+        assert_eq!(ctx, GLVersion::V4_3);
+    }
+
+    #[test]
+    fn negotiate_stops_at_other_errors() {
+        let conf = config(GLVersion::V4_6, GLVersionPolicy::DownTo(GLVersion::V3_0), None);
+        let mut tried = 0;
+        let error = conf
+            .negotiate(|_| -> Result<(), GLDKError> {
+                tried += 1;
+                Err(GLDKError::new_simple(ErrorKind::Unsupported("GLX_ARB_create_context".to_owned())))
+            })
+            .unwrap_err();
+        assert_eq!(tried, 1);
+        assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    }
 }
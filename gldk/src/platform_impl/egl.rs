@@ -0,0 +1,294 @@
+use crate::context::ContextInfo;
+use crate::error::{ErrorKind, GLDKError};
+use crate::sys::egl;
+use crate::sys::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLSurface, EGLenum, EGLint};
+use crate::{GLConfig, GLFramebufferFormat, GLProfile, GLResetNotification, GLVersion};
+use std::ffi::{c_void, CStr, CString};
+use std::ptr::null;
+
+/// An initialized EGL display.
+///
+/// Displays aren't terminated on drop: EGL hands out the same `EGLDisplay` for the same native display,
+/// so terminating one would invalidate the contexts of every other window or headless context using it.
+/// Owners of the native display call `terminate` before closing it.
+pub struct EGLConnection {
+    pub raw: EGLDisplay,
+    /// Whether the display was obtained through a platform extension, which decides
+    /// how native windows are passed to EGL.
+    platform: bool,
+    extensions: String,
+}
+
+impl EGLConnection {
+    /// Opens `native_display` through `platform` when the client supports `platform_extension`,
+    /// otherwise through plain `eglGetDisplay`.
+    pub unsafe fn open(
+        platform: EGLenum,
+        platform_extension: &str,
+        native_display: *mut c_void,
+    ) -> Result<Self, GLDKError> {
+        let client_extensions = to_string(egl::QueryString(egl::NO_DISPLAY, egl::EXTENSIONS as EGLint));
+        let platform_supported = client_extensions.split_whitespace().any(|e| e == platform_extension);
+        let raw = if platform_supported {
+            egl::GetPlatformDisplay(platform, native_display, null())
+        } else {
+            egl::GetDisplay(native_display as egl::types::EGLNativeDisplayType)
+        };
+        if raw == egl::NO_DISPLAY {
+            return Err(GLDKError::new_unexpected("Can't get EGL display.".to_owned()));
+        }
+
+        let (mut major, mut minor) = (0, 0);
+        if egl::Initialize(raw, &mut major, &mut minor) == egl::FALSE {
+            return Err(GLDKError::new_unexpected(format!(
+                "Can't initialize EGL display (0x{:x}).",
+                egl::GetError()
+            )));
+        }
+        // Context versions, profiles and flags are only part of the core API from EGL 1.5.
+        if (major, minor) < (1, 5) {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(format!(
+                "EGL 1.5 (display provides {}.{})",
+                major, minor
+            ))));
+        }
+
+        Ok(Self {
+            raw,
+            platform: platform_supported,
+            extensions: to_string(egl::QueryString(raw, egl::EXTENSIONS as EGLint)),
+        })
+    }
+
+    pub unsafe fn terminate(&self) {
+        egl::Terminate(self.raw);
+    }
+
+    pub fn is_extension_supported(&self, name: &str) -> bool {
+        self.extensions.split_whitespace().any(|e| e == name)
+    }
+
+    pub unsafe fn choose_config(
+        &self,
+        version: GLVersion,
+        format: &GLFramebufferFormat,
+        surface_type: EGLenum,
+    ) -> Result<EGLConfig, GLDKError> {
+        let renderable_type = match version.major_minor() {
+            _ if !version.is_es() => egl::OPENGL_BIT,
+            (2, _) => egl::OPENGL_ES2_BIT,
+            _ => egl::OPENGL_ES3_BIT,
+        };
+
+        let mut att = vec![
+            egl::SURFACE_TYPE,
+            surface_type,
+            egl::RENDERABLE_TYPE,
+            renderable_type,
+            egl::CONFORMANT,
+            renderable_type,
+            egl::COLOR_BUFFER_TYPE,
+            egl::RGB_BUFFER,
+            egl::RED_SIZE,
+            format.red_bits as EGLenum,
+            egl::GREEN_SIZE,
+            format.green_bits as EGLenum,
+            egl::BLUE_SIZE,
+            format.blue_bits as EGLenum,
+            egl::ALPHA_SIZE,
+            format.alpha_bits as EGLenum,
+            egl::DEPTH_SIZE,
+            format.depth_bits as EGLenum,
+            egl::STENCIL_SIZE,
+            format.stencil_bits as EGLenum,
+        ];
+        if format.samples > 0 {
+            att.push(egl::SAMPLE_BUFFERS);
+            att.push(1);
+            att.push(egl::SAMPLES);
+            att.push(format.samples as EGLenum);
+        }
+        att.push(egl::NONE);
+
+        let mut config = null();
+        let mut count = 0;
+        let result = egl::ChooseConfig(self.raw, att.as_ptr() as *const EGLint, &mut config, 1, &mut count);
+        if result == egl::FALSE || count == 0 {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(format!(
+                "No EGL config matches {:?}",
+                format
+            ))));
+        }
+
+        Ok(config)
+    }
+
+    pub unsafe fn config_attrib(&self, config: EGLConfig, attribute: EGLenum) -> EGLint {
+        let mut value = 0;
+        egl::GetConfigAttrib(self.raw, config, attribute as EGLint, &mut value);
+        value
+    }
+
+    /// `srgb` and `double_buffer` are properties of the surface rather than of the config in EGL,
+    /// so they are taken from the surface the caller created.
+    pub unsafe fn query_format(&self, config: EGLConfig, double_buffer: bool, srgb: bool) -> GLFramebufferFormat {
+        let attrib = |attribute: EGLenum| self.config_attrib(config, attribute) as u8;
+
+        GLFramebufferFormat {
+            red_bits: attrib(egl::RED_SIZE),
+            green_bits: attrib(egl::GREEN_SIZE),
+            blue_bits: attrib(egl::BLUE_SIZE),
+            alpha_bits: attrib(egl::ALPHA_SIZE),
+            depth_bits: attrib(egl::DEPTH_SIZE),
+            stencil_bits: attrib(egl::STENCIL_SIZE),
+            double_buffer,
+            samples: attrib(egl::SAMPLES),
+            srgb,
+        }
+    }
+
+    /// Surface attributes requesting an sRGB color space, terminated with `EGL_NONE`.
+    pub fn surface_attributes(&self, srgb: bool) -> Vec<EGLenum> {
+        let mut att = vec![];
+        if srgb {
+            att.push(egl::GL_COLORSPACE);
+            att.push(egl::GL_COLORSPACE_SRGB);
+        }
+        att.push(egl::NONE);
+        att
+    }
+
+    /// Platform displays take a pointer to the native window (e.g. to an Xlib `Window`),
+    /// while `eglCreateWindowSurface` takes the native window itself, so callers pass both.
+    pub unsafe fn create_window_surface(
+        &self,
+        config: EGLConfig,
+        platform_window: *mut c_void,
+        native_window: egl::types::EGLNativeWindowType,
+        srgb: bool,
+    ) -> Result<EGLSurface, GLDKError> {
+        let att = self.surface_attributes(srgb);
+        let surface = if self.platform {
+            let att: Vec<egl::types::EGLAttrib> = att.iter().map(|a| *a as egl::types::EGLAttrib).collect();
+            egl::CreatePlatformWindowSurface(self.raw, config, platform_window, att.as_ptr())
+        } else {
+            egl::CreateWindowSurface(self.raw, config, native_window, att.as_ptr() as *const EGLint)
+        };
+        if surface == egl::NO_SURFACE {
+            return Err(GLDKError::new_unexpected(format!(
+                "Can't create window surface (0x{:x}).",
+                egl::GetError()
+            )));
+        }
+
+        Ok(surface)
+    }
+
+    pub unsafe fn create_context(
+        &self,
+        config: EGLConfig,
+        share: EGLContext,
+        conf: &GLConfig,
+    ) -> Result<EGLContext, GLDKError> {
+        let api = if conf.version.is_es() {
+            egl::OPENGL_ES_API
+        } else {
+            egl::OPENGL_API
+        };
+        if egl::BindAPI(api) == egl::FALSE {
+            return Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)));
+        }
+
+        let (major, minor) = conf.version.major_minor();
+        let mut att = vec![
+            egl::CONTEXT_MAJOR_VERSION,
+            major,
+            egl::CONTEXT_MINOR_VERSION,
+            minor,
+        ];
+
+        if !conf.version.is_es() && conf.version.major_minor() >= (3, 2) {
+            att.push(egl::CONTEXT_OPENGL_PROFILE_MASK);
+            att.push(match conf.profile() {
+                GLProfile::Core => egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                GLProfile::Compatibility => egl::CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT,
+            });
+        }
+        if conf.flags.debug {
+            att.push(egl::CONTEXT_OPENGL_DEBUG);
+            att.push(egl::TRUE);
+        }
+        if conf.flags.forward_compatible && !conf.version.is_es() {
+            att.push(egl::CONTEXT_OPENGL_FORWARD_COMPATIBLE);
+            att.push(egl::TRUE);
+        }
+        if let Some(strategy) = conf.flags.robust_access {
+            att.push(egl::CONTEXT_OPENGL_ROBUST_ACCESS);
+            att.push(egl::TRUE);
+            att.push(egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY);
+            att.push(match strategy {
+                GLResetNotification::NoResetNotification => egl::NO_RESET_NOTIFICATION,
+                GLResetNotification::LoseContextOnReset => egl::LOSE_CONTEXT_ON_RESET,
+            });
+        }
+        if conf.flags.no_error {
+            if !self.is_extension_supported("EGL_KHR_create_context_no_error") {
+                return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                    "EGL_KHR_create_context_no_error".to_owned(),
+                )));
+            }
+            att.push(egl::CONTEXT_OPENGL_NO_ERROR_KHR);
+            att.push(egl::TRUE);
+        }
+        att.push(egl::NONE);
+
+        let ctx = egl::CreateContext(self.raw, config, share, att.as_ptr() as *const EGLint);
+        if ctx == egl::NO_CONTEXT {
+            return Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)));
+        }
+
+        Ok(ctx)
+    }
+}
+
+impl EGLConnection {
+    /// Negotiates a context for `config` and queries its `ContextInfo` through `surface`,
+    /// leaving whatever context was current untouched.
+    pub unsafe fn create_context_with_info(
+        &self,
+        config: EGLConfig,
+        surface: EGLSurface,
+        share: EGLContext,
+        conf: &GLConfig,
+    ) -> Result<(EGLContext, GLVersion, ContextInfo), GLDKError> {
+        let (ctx, version) = conf.negotiate(|conf| self.create_context(config, share, conf))?;
+
+        let previous = (
+            egl::GetCurrentDisplay(),
+            egl::GetCurrentSurface(egl::DRAW as EGLint),
+            egl::GetCurrentSurface(egl::READ as EGLint),
+            egl::GetCurrentContext(),
+        );
+        egl::MakeCurrent(self.raw, surface, surface, ctx);
+        let info = ContextInfo::query(version, get_proc_address);
+        if previous.0 == egl::NO_DISPLAY {
+            egl::MakeCurrent(self.raw, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+        } else {
+            egl::MakeCurrent(previous.0, previous.1, previous.2, previous.3);
+        }
+
+        Ok((ctx, version, info))
+    }
+}
+
+pub fn get_proc_address(addr: &str) -> *const c_void {
+    let addr = CString::new(addr.as_bytes()).unwrap();
+    unsafe { egl::GetProcAddress(addr.as_ptr()) as *const c_void }
+}
+
+unsafe fn to_string(s: *const std::os::raw::c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(s).to_string_lossy().into_owned()
+}
@@ -0,0 +1,111 @@
+use crate::context::ContextInfo;
+use crate::error::GLDKError;
+use crate::platform_impl::egl::{get_proc_address, EGLConnection};
+use crate::sys::egl;
+use crate::sys::egl::types::{EGLContext, EGLSurface, EGLint};
+use crate::{GLConfig, GLFramebufferFormat, GLVersion};
+use std::ffi::c_void;
+
+pub struct RHeadlessContext {
+    display: EGLConnection,
+    surface: EGLSurface,
+    ctx: EGLContext,
+    version: GLVersion,
+    info: Option<ContextInfo>,
+    format: GLFramebufferFormat,
+}
+
+impl RHeadlessContext {
+    pub fn new(width: u32, height: u32, conf: GLConfig) -> Result<Self, GLDKError> {
+        unsafe {
+            // Mesa can render without any display server through the surfaceless platform.
+            let display = EGLConnection::open(
+                egl::PLATFORM_SURFACELESS_MESA,
+                "EGL_MESA_platform_surfaceless",
+                egl::DEFAULT_DISPLAY as *mut c_void,
+            )?;
+
+            let config = display.choose_config(conf.version, &conf.framebuffer, egl::PBUFFER_BIT)?;
+
+            let mut context = Self {
+                display,
+                surface: egl::NO_SURFACE,
+                ctx: egl::NO_CONTEXT,
+                version: conf.version,
+                info: None,
+                format: conf.framebuffer,
+            };
+
+            let mut att = vec![egl::WIDTH, width, egl::HEIGHT, height];
+            att.extend(context.display.surface_attributes(conf.framebuffer.srgb));
+            context.surface =
+                egl::CreatePbufferSurface(context.display.raw, config, att.as_ptr() as *const EGLint);
+            if context.surface == egl::NO_SURFACE {
+                return Err(GLDKError::new_unexpected(format!(
+                    "Can't create pbuffer surface (0x{:x}).",
+                    egl::GetError()
+                )));
+            }
+            context.format = context
+                .display
+                .query_format(config, false, conf.framebuffer.srgb);
+
+            let (ctx, version, info) =
+                context
+                    .display
+                    .create_context_with_info(config, context.surface, egl::NO_CONTEXT, &conf)?;
+            context.ctx = ctx;
+            context.version = version;
+            context.info = Some(info);
+
+            Ok(context)
+        }
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const c_void {
+        get_proc_address(addr)
+    }
+
+    pub fn get_gl_version(&self) -> GLVersion {
+        self.version
+    }
+
+    pub fn get_context_info(&self) -> &ContextInfo {
+        self.info.as_ref().unwrap()
+    }
+
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        self.format
+    }
+
+    pub fn get_framebuffer_size(&self) -> (u32, u32) {
+        let (mut width, mut height) = (0, 0);
+        unsafe {
+            egl::QuerySurface(self.display.raw, self.surface, egl::WIDTH as EGLint, &mut width);
+            egl::QuerySurface(self.display.raw, self.surface, egl::HEIGHT as EGLint, &mut height);
+        }
+        (width as u32, height as u32)
+    }
+
+    pub fn make_current(&self) {
+        unsafe {
+            egl::MakeCurrent(self.display.raw, self.surface, self.surface, self.ctx);
+        }
+    }
+}
+
+impl Drop for RHeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            if egl::GetCurrentContext() == self.ctx {
+                egl::MakeCurrent(self.display.raw, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+            }
+            if self.ctx != egl::NO_CONTEXT {
+                egl::DestroyContext(self.display.raw, self.ctx);
+            }
+            if self.surface != egl::NO_SURFACE {
+                egl::DestroySurface(self.display.raw, self.surface);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::context::ContextInfo;
+use crate::error::{ErrorKind, GLDKError};
+use crate::platform_impl::window::{
+    choose_pixel_format, create_wgl_context, describe_pixel_format, get_proc_address, load_wgl_functions,
+};
+use crate::sys::wgl_extra::types::{HGLRC, HPBUFFERARB};
+use crate::sys::{wgl, wgl_extra, WGLARBFunctions};
+use crate::{GLConfig, GLContextBackend, GLFramebufferFormat, GLVersion};
+use core::ffi::c_void;
+use std::ptr::{null, null_mut};
+
+use winapi::shared::windef::HDC;
+use winapi::um::libloaderapi::GetModuleHandleA;
+use winapi::um::winuser::{GetDC, ReleaseDC};
+
+pub struct RHeadlessContext {
+    wgl: WGLARBFunctions,
+    pbuffer: HPBUFFERARB,
+    hdc: HDC,
+    ctx: HGLRC,
+    version: GLVersion,
+    info: Option<ContextInfo>,
+    format: GLFramebufferFormat,
+}
+
+impl RHeadlessContext {
+    pub fn new(width: u32, height: u32, conf: GLConfig) -> Result<Self, GLDKError> {
+        if conf.backend == GLContextBackend::Egl {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported("EGL".to_owned())));
+        }
+
+        unsafe {
+            let func = load_wgl_functions(GetModuleHandleA(null()))?;
+
+            // Pbuffers only need a device context to pick the device, the screen's will do.
+            let screen = GetDC(null_mut());
+            let result = Self::create(func, screen, width, height, conf);
+            ReleaseDC(null_mut(), screen);
+            result
+        }
+    }
+
+    unsafe fn create(
+        func: WGLARBFunctions,
+        screen: HDC,
+        width: u32,
+        height: u32,
+        conf: GLConfig,
+    ) -> Result<Self, GLDKError> {
+        let (create_pbuffer, get_pbuffer_dc) = match (func.wglCreatePbufferARB, func.wglGetPbufferDCARB) {
+            (Some(create), Some(get_dc))
+                if func.is_extension_supported(screen as wgl_extra::types::HDC, "WGL_ARB_pbuffer") =>
+            {
+                (create, get_dc)
+            }
+            _ => {
+                return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                    "WGL_ARB_pbuffer".to_owned(),
+                )))
+            }
+        };
+
+        let format = GLFramebufferFormat {
+            double_buffer: false,
+            ..conf.framebuffer
+        };
+        let pixel_format = choose_pixel_format(&func, screen, &format, wgl_extra::DRAW_TO_PBUFFER_ARB)?;
+
+        let attributes = [0];
+        let pbuffer = create_pbuffer(
+            screen as wgl_extra::types::HDC,
+            pixel_format,
+            width as i32,
+            height as i32,
+            attributes.as_ptr(),
+        );
+        if pbuffer.is_null() {
+            return Err(GLDKError::new_unexpected("Can't create pbuffer.".to_owned()));
+        }
+
+        let mut context = Self {
+            pbuffer,
+            hdc: get_pbuffer_dc(pbuffer) as HDC,
+            ctx: null(),
+            version: conf.version,
+            info: None,
+            format: describe_pixel_format(&func, screen, pixel_format),
+            wgl: func,
+        };
+
+        let (ctx, version) =
+            conf.negotiate(|conf| create_wgl_context(&context.wgl, context.hdc, null(), conf))?;
+        context.ctx = ctx;
+        context.version = version;
+
+        let (previous_dc, previous_ctx) = (wgl::GetCurrentDC(), wgl::GetCurrentContext());
+        context.make_current();
+        context.info = Some(ContextInfo::query(version, get_proc_address));
+        wgl::MakeCurrent(previous_dc, previous_ctx);
+
+        Ok(context)
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const c_void {
+        get_proc_address(addr)
+    }
+
+    pub fn get_gl_version(&self) -> GLVersion {
+        self.version
+    }
+
+    pub fn get_context_info(&self) -> &ContextInfo {
+        self.info.as_ref().unwrap()
+    }
+
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        self.format
+    }
+
+    pub fn get_framebuffer_size(&self) -> (u32, u32) {
+        let (mut width, mut height) = (0, 0);
+        if let Some(query) = self.wgl.wglQueryPbufferARB {
+            unsafe {
+                query(self.pbuffer, wgl_extra::PBUFFER_WIDTH_ARB as i32, &mut width);
+                query(self.pbuffer, wgl_extra::PBUFFER_HEIGHT_ARB as i32, &mut height);
+            }
+        }
+        (width as u32, height as u32)
+    }
+
+    pub fn make_current(&self) {
+        unsafe {
+            wgl::MakeCurrent(self.hdc as wgl::types::HDC, self.ctx as wgl::types::HGLRC);
+        }
+    }
+}
+
+impl Drop for RHeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.ctx.is_null() {
+                if wgl::GetCurrentContext() == self.ctx as wgl::types::HGLRC {
+                    wgl::MakeCurrent(null(), null());
+                }
+                wgl::DeleteContext(self.ctx as wgl::types::HGLRC);
+            }
+            if let Some(release_dc) = self.wgl.wglReleasePbufferDCARB {
+                release_dc(self.pbuffer, self.hdc as wgl_extra::types::HDC);
+            }
+            if let Some(destroy) = self.wgl.wglDestroyPbufferARB {
+                destroy(self.pbuffer);
+            }
+        }
+    }
+}
@@ -1 +1,5 @@
+pub(crate) mod headless;
 pub(crate) mod window;
+
+#[cfg(target_os = "linux")]
+pub(crate) mod egl;
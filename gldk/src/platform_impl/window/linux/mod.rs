@@ -0,0 +1,259 @@
+use crate::context::ContextInfo;
+use crate::error::{ErrorKind, GLDKError};
+use crate::sys::{poll, pollfd, POLLIN};
+use crate::window::{ControlFlow, KeyboardState, WindowEvent, WindowID};
+use crate::{GLConfig, GLFramebufferFormat, GLVersion};
+use core::ffi::c_void;
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+use std::os::raw::c_int;
+use std::rc::Rc;
+use std::time::Duration;
+
+#[cfg(feature = "wayland")]
+pub mod wayland;
+pub mod x11;
+
+/// Set to `x11` or `wayland` to force a backend, failing if it can't be used. Otherwise, with the
+/// `wayland` feature, Wayland sessions get the Wayland backend when the compositor can be reached,
+/// and everything else X11, through XWayland on Wayland sessions.
+const BACKEND_VAR: &str = "GLDK_UNIX_BACKEND";
+
+/// The display server connection windows are created on and whose events are read.
+pub enum REventLoop {
+    X11(Rc<x11::XConnection>),
+    #[cfg(feature = "wayland")]
+    Wayland(Rc<wayland::WaylandConnection>),
+}
+
+impl REventLoop {
+    pub fn new() -> Result<Self, GLDKError> {
+        match std::env::var(BACKEND_VAR).as_deref() {
+            Ok("x11") => x11::XConnection::open().map(REventLoop::X11),
+            #[cfg(feature = "wayland")]
+            Ok("wayland") => wayland::WaylandConnection::connect().map(REventLoop::Wayland),
+            #[cfg(not(feature = "wayland"))]
+            Ok("wayland") => Err(GLDKError::new_simple(ErrorKind::InvalidConfig(format!(
+                "{}=wayland needs gldk to be built with the \"wayland\" feature.",
+                BACKEND_VAR
+            )))),
+            Ok(other) => Err(GLDKError::new_simple(ErrorKind::InvalidConfig(format!(
+                "Unknown {} \"{}\", expected \"x11\" or \"wayland\".",
+                BACKEND_VAR, other
+            )))),
+            Err(_) => {
+                #[cfg(feature = "wayland")]
+                if Self::wayland_session() {
+                    if let Ok(connection) = wayland::WaylandConnection::connect() {
+                        return Ok(REventLoop::Wayland(connection));
+                    }
+                }
+                x11::XConnection::open().map(REventLoop::X11)
+            }
+        }
+    }
+
+    /// Whether there is a compositor to try at all, so X11 sessions don't probe for one.
+    #[cfg(feature = "wayland")]
+    fn wayland_session() -> bool {
+        std::env::var_os("WAYLAND_DISPLAY").is_some() || std::env::var_os("WAYLAND_SOCKET").is_some()
+    }
+
+    /// Shared windows have to live on the same connection as the window they share with.
+    fn of(window: &RWindow) -> Self {
+        match window {
+            RWindow::X11(window) => REventLoop::X11(window.connection().clone()),
+            #[cfg(feature = "wayland")]
+            RWindow::Wayland(window) => REventLoop::Wayland(window.connection().clone()),
+        }
+    }
+
+    pub fn create_window(
+        &self,
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: GLConfig,
+        share: Option<&RWindow>,
+    ) -> Result<RWindow, GLDKError> {
+        match (self, share) {
+            (REventLoop::X11(connection), None) => {
+                x11::RWindow::new(connection.clone(), width, height, title, conf, None).map(RWindow::X11)
+            }
+            (REventLoop::X11(connection), Some(RWindow::X11(share)))
+                if Rc::ptr_eq(connection, share.connection()) =>
+            {
+                x11::RWindow::new(connection.clone(), width, height, title, conf, Some(share)).map(RWindow::X11)
+            }
+            #[cfg(feature = "wayland")]
+            (REventLoop::Wayland(connection), None) => {
+                wayland::RWindow::new(connection.clone(), width, height, title, conf, None).map(RWindow::Wayland)
+            }
+            #[cfg(feature = "wayland")]
+            (REventLoop::Wayland(connection), Some(RWindow::Wayland(share)))
+                if Rc::ptr_eq(connection, share.connection()) =>
+            {
+                wayland::RWindow::new(connection.clone(), width, height, title, conf, Some(share))
+                    .map(RWindow::Wayland)
+            }
+            _ => Err(GLDKError::new_simple(ErrorKind::InvalidConfig(
+                "Windows can only share with windows created from the same event loop.".to_owned(),
+            ))),
+        }
+    }
+
+    /// Blocks for at most `timeout`, or until an event arrives if it is `None`.
+    pub fn wait_event(&self, timeout: Option<Duration>) -> Result<Option<(WindowID, WindowEvent)>, GLDKError> {
+        match self {
+            REventLoop::X11(connection) => connection.wait_event(timeout),
+            #[cfg(feature = "wayland")]
+            REventLoop::Wayland(connection) => connection.wait_event(timeout),
+        }
+    }
+}
+
+/// Whether `fd` became readable within `timeout`, rounded up to the millisecond so short waits
+/// don't spin.
+fn wait_readable(fd: c_int, timeout: Duration) -> bool {
+    let mut fds = pollfd {
+        fd,
+        events: POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_micros().div_ceil(1000).min(c_int::MAX as u128) as c_int;
+    unsafe { poll(&mut fds, 1, millis) > 0 }
+}
+
+pub enum RWindow {
+    X11(x11::RWindow),
+    #[cfg(feature = "wayland")]
+    Wayland(wayland::RWindow),
+}
+
+macro_rules! dispatch {
+    ($self:ident, $window:ident => $body:expr) => {
+        match $self {
+            RWindow::X11($window) => $body,
+            #[cfg(feature = "wayland")]
+            RWindow::Wayland($window) => $body,
+        }
+    };
+}
+
+impl RWindow {
+    pub fn new(
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: GLConfig,
+        share: Option<&RWindow>,
+    ) -> Result<Self, GLDKError> {
+        let event_loop = match share {
+            Some(share) => REventLoop::of(share),
+            None => REventLoop::new()?,
+        };
+        event_loop.create_window(width, height, title, conf, share)
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const c_void {
+        dispatch!(self, w => w.get_proc_address(addr))
+    }
+
+    pub fn get_gl_version(&self) -> GLVersion {
+        dispatch!(self, w => w.get_gl_version())
+    }
+
+    pub fn get_context_info(&self) -> &ContextInfo {
+        dispatch!(self, w => w.get_context_info())
+    }
+
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        dispatch!(self, w => w.get_framebuffer_format())
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        dispatch!(self, w => w.scale_factor())
+    }
+
+    pub fn handle(&self) -> RawWindowHandle {
+        dispatch!(self, w => w.handle())
+    }
+
+    pub fn display_handle(&self) -> RawDisplayHandle {
+        dispatch!(self, w => w.display_handle())
+    }
+
+    pub fn id(&self) -> WindowID {
+        dispatch!(self, w => w.id())
+    }
+
+    pub fn swap_buffers(&self) {
+        dispatch!(self, w => w.swap_buffers())
+    }
+
+    pub fn make_current(&self) {
+        dispatch!(self, w => w.make_current())
+    }
+
+    pub fn swap_interval(&self, enable: bool) {
+        dispatch!(self, w => w.swap_interval(enable))
+    }
+
+    pub fn run<F>(&self, callback: F) -> i32
+    where
+        F: FnMut(WindowEvent, &mut ControlFlow),
+    {
+        dispatch!(self, w => w.run(callback))
+    }
+
+    pub fn show(&self) {
+        dispatch!(self, w => w.show())
+    }
+
+    pub fn hide(&self) {
+        dispatch!(self, w => w.hide())
+    }
+
+    pub fn set_window_title(&self, title: &str) {
+        dispatch!(self, w => w.set_window_title(title))
+    }
+
+    pub fn set_window_border_width(&self, width: u32) {
+        dispatch!(self, w => w.set_window_border_width(width))
+    }
+
+    pub fn get_window_size(&self) -> (u32, u32) {
+        dispatch!(self, w => w.get_window_size())
+    }
+
+    pub fn get_window_pos(&self) -> (u32, u32) {
+        dispatch!(self, w => w.get_window_pos())
+    }
+
+    pub fn set_undecorated(&self, b: bool) {
+        dispatch!(self, w => w.set_undecorated(b))
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        dispatch!(self, w => w.set_maximized(maximized))
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        dispatch!(self, w => w.set_minimized(minimized))
+    }
+
+    pub fn keyboard_state(&self) -> KeyboardState {
+        dispatch!(self, w => w.keyboard_state())
+    }
+
+    pub fn set_ime_enabled(&self, enabled: bool) {
+        dispatch!(self, w => w.set_ime_enabled(enabled))
+    }
+
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        dispatch!(self, w => w.set_ime_position(x, y))
+    }
+
+    pub fn quit(&self) {
+        dispatch!(self, w => w.quit())
+    }
+}
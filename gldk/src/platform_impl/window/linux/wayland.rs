@@ -0,0 +1,1398 @@
+use crate::context::ContextInfo;
+use crate::error::{ErrorKind, GLDKError};
+use super::x11;
+use crate::platform_impl::egl::{self as egl_impl, EGLConnection};
+use crate::sys::egl;
+use crate::sys::egl::types::{EGLContext, EGLSurface};
+use crate::sys::wayland::*;
+use crate::sys::xkbcommon::*;
+use crate::window::{
+    ControlFlow, KeyboardInput, KeyboardState, Modifiers, MouseButton, Position, ScrollDelta, WindowEvent, WindowID,
+};
+use crate::{GLConfig, GLFramebufferFormat, GLVersion};
+use core::ffi::c_void;
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::os::raw::{c_char, c_int};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::FromRawFd;
+use std::ptr::{addr_of, null, null_mut};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Globals bound from the registry and the devices of the seat, boxed so the listeners can keep
+/// writing to them.
+struct Globals {
+    compositor: Cell<*mut wl_proxy>,
+    wm_base: Cell<*mut wl_proxy>,
+    seat: Cell<*mut wl_proxy>,
+    pointer: Cell<*mut wl_proxy>,
+    keyboard: Cell<*mut wl_proxy>,
+    outputs: RefCell<Vec<Output>>,
+    /// Every window's state, to rescale them when the scale of an output changes.
+    windows: RefCell<Vec<*const State>>,
+    /// The surfaces the pointer and the keyboard are on, or null.
+    pointer_focus: Cell<*mut wl_proxy>,
+    keyboard_focus: Cell<*mut wl_proxy>,
+    /// Scrolling since the last `wl_pointer.frame`, in surface coordinates and in wheel notches.
+    axis: Cell<((f64, f64), (i32, i32))>,
+    xkb: RefCell<Option<Xkb>>,
+    /// Keys per second and the delay before the first repeat in milliseconds, 0 keys per second
+    /// disabling repeats.
+    repeat_info: Cell<(i32, i32)>,
+    repeat: Cell<Option<Repeat>>,
+}
+
+impl Globals {
+    /// Reports a key of the keyboard the compositor sent the keymap of, pressed or released.
+    fn key_event(&self, state: &State, keycode: xkb_keycode_t, pressed: bool) {
+        let xkb = self.xkb.borrow();
+        let Some(xkb) = xkb.as_ref() else {
+            return;
+        };
+        let mut input = xkb.keyboard_input(keycode);
+        if pressed {
+            input.repeat = state.keyboard.borrow_mut().press(&input);
+            state.push_event(WindowEvent::Keydown(input));
+            for c in xkb.text(keycode).chars() {
+                state.push_event(WindowEvent::ReceivedCharacter(c));
+            }
+        } else {
+            state.keyboard.borrow_mut().release(&input);
+            state.push_event(WindowEvent::Keyup(input));
+        }
+    }
+}
+
+struct Output {
+    proxy: *mut wl_proxy,
+    /// The registry name, to know which output a `global_remove` is about.
+    name: u32,
+    scale: Cell<i32>,
+}
+
+/// A key held down, which `wl_keyboard` leaves to clients to repeat.
+#[derive(Clone, Copy)]
+struct Repeat {
+    surface: *mut wl_proxy,
+    keycode: xkb_keycode_t,
+    next: Instant,
+}
+
+/// The compositor's keymap, the state of its modifiers and the compose sequence being typed, if
+/// the locale has any.
+struct Xkb {
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+    compose_table: *mut xkb_compose_table,
+    compose_state: *mut xkb_compose_state,
+}
+
+impl Xkb {
+    fn new(keymap: &CStr) -> Option<Self> {
+        unsafe {
+            let context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let mut xkb = Self {
+                context,
+                keymap: null_mut(),
+                state: null_mut(),
+                compose_table: null_mut(),
+                compose_state: null_mut(),
+            };
+            xkb.keymap = xkb_keymap_new_from_string(
+                context,
+                keymap.as_ptr(),
+                XKB_KEYMAP_FORMAT_TEXT_V1,
+                XKB_KEYMAP_COMPILE_NO_FLAGS,
+            );
+            if xkb.keymap.is_null() {
+                return None;
+            }
+            xkb.state = xkb_state_new(xkb.keymap);
+            if xkb.state.is_null() {
+                return None;
+            }
+
+            // Compose sequences are optional, they depend on the locale as with Xlib's input method.
+            let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find(|locale| !locale.is_empty())
+                .unwrap_or_else(|| "C".to_owned());
+            let locale = CString::new(locale).unwrap_or_default();
+            xkb.compose_table =
+                xkb_compose_table_new_from_locale(context, locale.as_ptr(), XKB_COMPOSE_COMPILE_NO_FLAGS);
+            if !xkb.compose_table.is_null() {
+                xkb.compose_state = xkb_compose_state_new(xkb.compose_table, XKB_COMPOSE_STATE_NO_FLAGS);
+            }
+            Some(xkb)
+        }
+    }
+
+    fn keyboard_input(&self, keycode: xkb_keycode_t) -> KeyboardInput {
+        unsafe {
+            let layout = xkb_state_key_get_layout(self.state, keycode);
+            let keysym = x11::key_keysym(|level| {
+                let mut syms = null();
+                if xkb_keymap_key_get_syms_by_level(self.keymap, keycode, layout, level as u32, &mut syms) > 0 {
+                    *syms
+                } else {
+                    0
+                }
+            });
+            let active = |name: &[u8]| {
+                xkb_state_mod_name_is_active(self.state, name.as_ptr() as *const c_char, XKB_STATE_MODS_EFFECTIVE) > 0
+            };
+
+            KeyboardInput {
+                keycode: x11::virtual_keycode(keysym),
+                scancode: keycode,
+                modifiers: Modifiers {
+                    shift: active(XKB_MOD_NAME_SHIFT),
+                    ctrl: active(XKB_MOD_NAME_CTRL),
+                    alt: active(XKB_MOD_NAME_ALT),
+                    super_key: active(XKB_MOD_NAME_LOGO),
+                    caps_lock: active(XKB_MOD_NAME_CAPS),
+                    num_lock: active(XKB_MOD_NAME_NUM),
+                },
+                // Only the window's keyboard state knows.
+                repeat: false,
+            }
+        }
+    }
+
+    /// The text a key press types, nothing while a compose sequence is in progress.
+    fn text(&self, keycode: xkb_keycode_t) -> String {
+        unsafe {
+            let keysym = xkb_state_key_get_one_sym(self.state, keycode);
+            if !self.compose_state.is_null()
+                && xkb_compose_state_feed(self.compose_state, keysym) == XKB_COMPOSE_FEED_ACCEPTED
+            {
+                match xkb_compose_state_get_status(self.compose_state) {
+                    XKB_COMPOSE_COMPOSING => return String::new(),
+                    XKB_COMPOSE_COMPOSED => {
+                        let text = utf8(|buffer, size| xkb_compose_state_get_utf8(self.compose_state, buffer, size));
+                        xkb_compose_state_reset(self.compose_state);
+                        return text;
+                    }
+                    XKB_COMPOSE_CANCELLED => {
+                        xkb_compose_state_reset(self.compose_state);
+                        return String::new();
+                    }
+                    _ => {}
+                }
+            }
+            utf8(|buffer, size| xkb_state_key_get_utf8(self.state, keycode, buffer, size))
+        }
+    }
+
+    fn repeats(&self, keycode: xkb_keycode_t) -> bool {
+        unsafe { xkb_keymap_key_repeats(self.keymap, keycode) != 0 }
+    }
+}
+
+impl Drop for Xkb {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.compose_state.is_null() {
+                xkb_compose_state_unref(self.compose_state);
+            }
+            if !self.compose_table.is_null() {
+                xkb_compose_table_unref(self.compose_table);
+            }
+            if !self.state.is_null() {
+                xkb_state_unref(self.state);
+            }
+            if !self.keymap.is_null() {
+                xkb_keymap_unref(self.keymap);
+            }
+            xkb_context_unref(self.context);
+        }
+    }
+}
+
+/// Reads the text libxkbcommon writes with `snprintf` conventions.
+fn utf8(write: impl FnOnce(*mut c_char, usize) -> c_int) -> String {
+    let mut buffer = [0u8; 64];
+    let written = write(buffer.as_mut_ptr() as *mut c_char, buffer.len());
+    if written <= 0 {
+        return String::new();
+    }
+    let written = (written as usize).min(buffer.len() - 1);
+    String::from_utf8_lossy(&buffer[..written]).into_owned()
+}
+
+/// A connection to the compositor shared by every window created from it, closed with the last one.
+pub struct WaylandConnection {
+    display: *mut wl_display,
+    registry: *mut wl_proxy,
+    globals: Box<Globals>,
+    egl: Option<EGLConnection>,
+    /// Events of every window on this connection, in the order the listeners saw them.
+    events: Rc<RefCell<VecDeque<(WindowID, WindowEvent)>>>,
+}
+
+impl WaylandConnection {
+    /// Fails when there is no compositor to connect to, or when it lacks what GLDK needs.
+    pub fn connect() -> Result<Rc<Self>, GLDKError> {
+        unsafe {
+            let display = wl_display_connect(null());
+            if display.is_null() {
+                return Err(GLDKError::new_unexpected(
+                    "Can't connect to a Wayland compositor.".to_owned(),
+                ));
+            }
+
+            let registry = wl_proxy_marshal_flags(
+                display as *mut wl_proxy,
+                WL_DISPLAY_GET_REGISTRY,
+                addr_of!(wl_registry_interface),
+                wl_proxy_get_version(display as *mut wl_proxy),
+                0,
+                null_mut::<c_void>(),
+            );
+            let mut connection = Self {
+                display,
+                registry,
+                globals: Box::new(Globals {
+                    compositor: Cell::new(null_mut()),
+                    wm_base: Cell::new(null_mut()),
+                    seat: Cell::new(null_mut()),
+                    pointer: Cell::new(null_mut()),
+                    keyboard: Cell::new(null_mut()),
+                    outputs: RefCell::new(Vec::new()),
+                    windows: RefCell::new(Vec::new()),
+                    pointer_focus: Cell::new(null_mut()),
+                    keyboard_focus: Cell::new(null_mut()),
+                    axis: Cell::new(Default::default()),
+                    xkb: RefCell::new(None),
+                    repeat_info: Cell::new((25, 600)),
+                    repeat: Cell::new(None),
+                }),
+                egl: None,
+                events: Rc::new(RefCell::new(VecDeque::new())),
+            };
+            wl_proxy_add_listener(
+                registry,
+                addr_of!(REGISTRY_LISTENER) as *const c_void,
+                &*connection.globals as *const Globals as *mut c_void,
+            );
+            wl_display_roundtrip(display);
+
+            if connection.globals.compositor.get().is_null() {
+                return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                    "wl_compositor".to_owned(),
+                )));
+            }
+            if connection.globals.wm_base.get().is_null() {
+                return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                    "xdg_wm_base".to_owned(),
+                )));
+            }
+            wl_proxy_add_listener(
+                connection.globals.wm_base.get(),
+                addr_of!(WM_BASE_LISTENER) as *const c_void,
+                null_mut(),
+            );
+
+            connection.egl = Some(EGLConnection::open(
+                egl::PLATFORM_WAYLAND_KHR,
+                "EGL_KHR_platform_wayland",
+                display as *mut c_void,
+            )?);
+
+            Ok(Rc::new(connection))
+        }
+    }
+
+    fn egl(&self) -> &EGLConnection {
+        self.egl.as_ref().unwrap()
+    }
+
+    /// Returns the next queued event, blocking until the compositor sends something if there is none,
+    /// for at most `timeout` unless it is `None`. Returns early, with nothing, to repeat a held key.
+    pub fn wait_event(&self, timeout: Option<Duration>) -> Result<Option<(WindowID, WindowEvent)>, GLDKError> {
+        self.repeat_key();
+        if let Some(event) = self.events.borrow_mut().pop_front() {
+            return Ok(Some(event));
+        }
+        let timeout = match self.globals.repeat.get() {
+            Some(repeat) => {
+                let until_repeat = repeat.next.saturating_duration_since(Instant::now());
+                Some(timeout.map_or(until_repeat, |timeout| timeout.min(until_repeat)))
+            }
+            None => timeout,
+        };
+        let dispatched = match timeout {
+            None => unsafe { wl_display_dispatch(self.display) },
+            Some(timeout) => unsafe { self.dispatch_timeout(timeout) },
+        };
+        if dispatched < 0 {
+            return Err(GLDKError::new_unexpected(
+                "Lost the connection to the Wayland compositor.".to_owned(),
+            ));
+        }
+        self.repeat_key();
+        Ok(self.events.borrow_mut().pop_front())
+    }
+
+    /// Presses the held key again once it is time to.
+    fn repeat_key(&self) {
+        let globals = &self.globals;
+        let Some(mut repeat) = globals.repeat.get() else {
+            return;
+        };
+        let now = Instant::now();
+        if now < repeat.next {
+            return;
+        }
+        let (rate, _) = globals.repeat_info.get();
+        if rate <= 0 {
+            globals.repeat.set(None);
+            return;
+        }
+        repeat.next = now + Duration::from_secs(1) / rate as u32;
+        globals.repeat.set(Some(repeat));
+        if let Some(state) = unsafe { window_state(repeat.surface) } {
+            globals.key_event(state, repeat.keycode, true);
+        }
+    }
+
+    /// `wl_display_dispatch` with a timeout, following the protocol libwayland sets for reading
+    /// the socket from another poll loop.
+    unsafe fn dispatch_timeout(&self, timeout: Duration) -> c_int {
+        // Events already read have to be dispatched before the socket can be read.
+        while wl_display_prepare_read(self.display) != 0 {
+            if wl_display_dispatch_pending(self.display) < 0 {
+                return -1;
+            }
+        }
+        if !self.events.borrow().is_empty() {
+            wl_display_cancel_read(self.display);
+            return 0;
+        }
+        wl_display_flush(self.display);
+        if super::wait_readable(wl_display_get_fd(self.display), timeout) {
+            if wl_display_read_events(self.display) < 0 {
+                return -1;
+            }
+        } else {
+            wl_display_cancel_read(self.display);
+        }
+        wl_display_dispatch_pending(self.display)
+    }
+}
+
+impl Drop for WaylandConnection {
+    fn drop(&mut self) {
+        unsafe {
+            // The EGL display belongs to this connection alone, unlike with Xlib.
+            if let Some(egl) = &self.egl {
+                egl.terminate();
+            }
+            let wm_base = self.globals.wm_base.get();
+            if !wm_base.is_null() {
+                wl_proxy_marshal_flags(
+                    wm_base,
+                    XDG_WM_BASE_DESTROY,
+                    null(),
+                    wl_proxy_get_version(wm_base),
+                    WL_MARSHAL_FLAG_DESTROY,
+                );
+            }
+            let globals = &self.globals;
+            for output in globals.outputs.borrow_mut().drain(..) {
+                wl_proxy_destroy(output.proxy);
+            }
+            if !globals.pointer.get().is_null() {
+                release(globals.pointer.get(), WL_POINTER_RELEASE, 3);
+            }
+            if !globals.keyboard.get().is_null() {
+                release(globals.keyboard.get(), WL_KEYBOARD_RELEASE, 3);
+            }
+            if !globals.seat.get().is_null() {
+                release(globals.seat.get(), WL_SEAT_RELEASE, 5);
+            }
+            let compositor = globals.compositor.get();
+            if !compositor.is_null() {
+                wl_proxy_destroy(compositor);
+            }
+            wl_proxy_destroy(self.registry);
+            wl_display_disconnect(self.display);
+        }
+    }
+}
+
+/// Window state written by the listeners while the connection is dispatched, also the user data
+/// of the window's surface so seat events can be routed to it.
+struct State {
+    globals: *const Globals,
+    surface: Cell<*mut wl_proxy>,
+    egl_window: Cell<*mut wl_egl_window>,
+    /// In surface coordinates, `scale` times smaller than the buffer.
+    size: Cell<(u32, u32)>,
+    pending_size: Cell<Option<(u32, u32)>>,
+    scale: Cell<i32>,
+    /// The outputs the surface is on.
+    outputs: RefCell<Vec<*mut wl_proxy>>,
+    configured: Cell<bool>,
+    focused: Cell<bool>,
+    keyboard: RefCell<KeyboardState>,
+    id: Cell<WindowID>,
+    events: Rc<RefCell<VecDeque<(WindowID, WindowEvent)>>>,
+}
+
+impl State {
+    fn push_event(&self, event: WindowEvent) {
+        self.events.borrow_mut().push_back((self.id.get(), event));
+    }
+
+    fn physical_size(&self) -> (u32, u32) {
+        let (width, height) = self.size.get();
+        let scale = self.scale.get() as u32;
+        (width * scale, height * scale)
+    }
+
+    fn push_cursor_moved(&self, x: wl_fixed_t, y: wl_fixed_t) {
+        let scale = self.scale.get() as f64;
+        let physical = Position {
+            x: wl_fixed_to_double(x) * scale,
+            y: wl_fixed_to_double(y) * scale,
+        };
+        self.push_event(WindowEvent::CursorMoved {
+            physical,
+            logical: physical.to_logical(scale),
+        });
+    }
+
+    /// Follows the largest scale of the outputs the surface is on, so it is never blurry.
+    unsafe fn update_scale(&self) {
+        let surface = self.surface.get();
+        // Buffer scales came with version 3 of wl_surface.
+        if wl_proxy_get_version(surface) < 3 {
+            return;
+        }
+        let outputs = (*self.globals).outputs.borrow();
+        let scale = self
+            .outputs
+            .borrow()
+            .iter()
+            .filter_map(|proxy| outputs.iter().find(|output| output.proxy == *proxy))
+            .map(|output| output.scale.get())
+            .max();
+        // A surface that left every output keeps its scale.
+        let Some(scale) = scale.filter(|&scale| scale > 0) else {
+            return;
+        };
+        if self.scale.replace(scale) == scale {
+            return;
+        }
+
+        // Both take effect with the next buffer.
+        wl_proxy_marshal_flags(
+            surface,
+            WL_SURFACE_SET_BUFFER_SCALE,
+            null(),
+            wl_proxy_get_version(surface),
+            0,
+            scale,
+        );
+        let egl_window = self.egl_window.get();
+        if !egl_window.is_null() {
+            let (width, height) = self.physical_size();
+            wl_egl_window_resize(egl_window, width as c_int, height as c_int, 0, 0);
+            self.push_event(WindowEvent::Resized(width, height));
+            self.push_event(WindowEvent::RedrawRequested);
+        }
+    }
+}
+
+/// The window a surface belongs to. Every surface on the connection is a window's, but events name
+/// surfaces destroyed in the meantime as null.
+unsafe fn window_state<'a>(surface: *mut wl_proxy) -> Option<&'a State> {
+    if surface.is_null() {
+        return None;
+    }
+    (wl_proxy_get_user_data(surface) as *const State).as_ref()
+}
+
+pub struct RWindow {
+    connection: Rc<WaylandConnection>,
+    state: Box<State>,
+    surface: *mut wl_proxy,
+    xdg_surface: *mut wl_proxy,
+    xdg_toplevel: *mut wl_proxy,
+    egl_surface: EGLSurface,
+    ctx: EGLContext,
+    version: GLVersion,
+    info: Option<ContextInfo>,
+    format: GLFramebufferFormat,
+    running: Cell<bool>,
+}
+
+impl RWindow {
+    /// `GLConfig::backend` is ignored, contexts are always created through EGL on Wayland.
+    pub fn new(
+        connection: Rc<WaylandConnection>,
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: GLConfig,
+        share: Option<&RWindow>,
+    ) -> Result<Self, GLDKError> {
+        unsafe {
+            let config = connection
+                .egl()
+                .choose_config(conf.version, &conf.framebuffer, egl::WINDOW_BIT)?;
+
+            // Every resource created from here on is released by Drop if a later step fails.
+            let mut rwindow = Self {
+                state: Box::new(State {
+                    globals: &*connection.globals,
+                    surface: Cell::new(null_mut()),
+                    egl_window: Cell::new(null_mut()),
+                    size: Cell::new((width, height)),
+                    pending_size: Cell::new(None),
+                    scale: Cell::new(1),
+                    outputs: RefCell::new(Vec::new()),
+                    configured: Cell::new(false),
+                    focused: Cell::new(false),
+                    keyboard: RefCell::new(KeyboardState::default()),
+                    id: Cell::new(WindowID(0)),
+                    events: connection.events.clone(),
+                }),
+                connection,
+                surface: null_mut(),
+                xdg_surface: null_mut(),
+                xdg_toplevel: null_mut(),
+                egl_surface: egl::NO_SURFACE,
+                ctx: egl::NO_CONTEXT,
+                version: conf.version,
+                info: None,
+                format: conf.framebuffer,
+                running: Cell::new(false),
+            };
+            let state = &*rwindow.state as *const State as *mut c_void;
+
+            let compositor = rwindow.connection.globals.compositor.get();
+            rwindow.surface = wl_proxy_marshal_flags(
+                compositor,
+                WL_COMPOSITOR_CREATE_SURFACE,
+                addr_of!(wl_surface_interface),
+                wl_proxy_get_version(compositor),
+                0,
+                null_mut::<c_void>(),
+            );
+            rwindow.state.id.set(rwindow.id());
+            rwindow.state.surface.set(rwindow.surface);
+            wl_proxy_add_listener(rwindow.surface, addr_of!(SURFACE_LISTENER) as *const c_void, state);
+            rwindow.connection.globals.windows.borrow_mut().push(&*rwindow.state);
+
+            let wm_base = rwindow.connection.globals.wm_base.get();
+            rwindow.xdg_surface = wl_proxy_marshal_flags(
+                wm_base,
+                XDG_WM_BASE_GET_XDG_SURFACE,
+                addr_of!(xdg_surface_interface),
+                wl_proxy_get_version(wm_base),
+                0,
+                null_mut::<c_void>(),
+                rwindow.surface,
+            );
+            wl_proxy_add_listener(
+                rwindow.xdg_surface,
+                addr_of!(XDG_SURFACE_LISTENER) as *const c_void,
+                state,
+            );
+
+            rwindow.xdg_toplevel = wl_proxy_marshal_flags(
+                rwindow.xdg_surface,
+                XDG_SURFACE_GET_TOPLEVEL,
+                addr_of!(xdg_toplevel_interface),
+                wl_proxy_get_version(rwindow.xdg_surface),
+                0,
+                null_mut::<c_void>(),
+            );
+            wl_proxy_add_listener(
+                rwindow.xdg_toplevel,
+                addr_of!(XDG_TOPLEVEL_LISTENER) as *const c_void,
+                state,
+            );
+            rwindow.set_window_title(title);
+
+            // A buffer may only be attached once the initial configure has been acknowledged.
+            rwindow.commit();
+            while !rwindow.state.configured.get() {
+                if wl_display_dispatch(rwindow.connection.display) < 0 {
+                    return Err(GLDKError::new_unexpected(
+                        "Lost the connection to the Wayland compositor.".to_owned(),
+                    ));
+                }
+            }
+
+            let (width, height) = rwindow.state.physical_size();
+            let egl_window = wl_egl_window_create(rwindow.surface, width as c_int, height as c_int);
+            if egl_window.is_null() {
+                return Err(GLDKError::new_unexpected(
+                    "Can't create wl_egl_window.".to_owned(),
+                ));
+            }
+            rwindow.state.egl_window.set(egl_window);
+
+            let egl = rwindow.connection.egl();
+            rwindow.egl_surface = egl.create_window_surface(
+                config,
+                egl_window as *mut c_void,
+                egl_window as egl::types::EGLNativeWindowType,
+                conf.framebuffer.srgb,
+            )?;
+            // Window surfaces are always back buffered.
+            rwindow.format = egl.query_format(config, true, conf.framebuffer.srgb);
+
+            let share_ctx = share.map_or(egl::NO_CONTEXT, |share| share.ctx);
+            let (ctx, version, info) =
+                egl.create_context_with_info(config, rwindow.egl_surface, share_ctx, &conf)?;
+            rwindow.ctx = ctx;
+            rwindow.version = version;
+            rwindow.info = Some(info);
+
+            Ok(rwindow)
+        }
+    }
+
+    pub fn connection(&self) -> &Rc<WaylandConnection> {
+        &self.connection
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const c_void {
+        egl_impl::get_proc_address(addr)
+    }
+
+    pub fn get_gl_version(&self) -> GLVersion {
+        self.version
+    }
+
+    pub fn get_context_info(&self) -> &ContextInfo {
+        self.info.as_ref().unwrap()
+    }
+
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        self.format
+    }
+
+    /// The largest scale of the outputs the window is on, whole numbers only.
+    pub fn scale_factor(&self) -> f64 {
+        self.state.scale.get() as f64
+    }
+
+    pub fn handle(&self) -> RawWindowHandle {
+        let mut window_handle = WaylandWindowHandle::empty();
+        window_handle.surface = self.surface as *mut c_void;
+        RawWindowHandle::Wayland(window_handle)
+    }
+
+    pub fn display_handle(&self) -> RawDisplayHandle {
+        let mut display_handle = WaylandDisplayHandle::empty();
+        display_handle.display = self.connection.display as *mut c_void;
+        RawDisplayHandle::Wayland(display_handle)
+    }
+
+    pub fn id(&self) -> WindowID {
+        WindowID(self.surface as u64)
+    }
+
+    pub fn swap_buffers(&self) {
+        unsafe {
+            egl::SwapBuffers(self.connection.egl().raw, self.egl_surface);
+        }
+    }
+
+    pub fn make_current(&self) {
+        unsafe {
+            egl::MakeCurrent(self.connection.egl().raw, self.egl_surface, self.egl_surface, self.ctx);
+        }
+    }
+
+    pub fn swap_interval(&self, enable: bool) {
+        unsafe {
+            egl::SwapInterval(self.connection.egl().raw, enable as egl::types::EGLint);
+        }
+    }
+
+    pub fn run<F>(&self, mut callback: F) -> i32
+    where
+        F: FnMut(WindowEvent, &mut ControlFlow),
+    {
+        let id = self.id();
+        let mut control_flow = ControlFlow::default();
+        self.running.set(true);
+        loop {
+            if let ControlFlow::Exit(code) = control_flow {
+                return code;
+            }
+            if !self.running.get() {
+                return 0;
+            }
+            match self.connection.wait_event(control_flow.timeout()) {
+                Ok(Some((event_id, event))) if event_id == id => callback(event, &mut control_flow),
+                Ok(None) if control_flow.is_due() => callback(WindowEvent::RedrawRequested, &mut control_flow),
+                Ok(_) => {}
+                Err(_) => return 1,
+            }
+        }
+    }
+
+    /// Toplevels are mapped by the first `swap_buffers`, there is nothing else to do.
+    pub fn show(&self) {}
+
+    /// Wayland clients can only hide a toplevel by destroying its role, which isn't supported.
+    pub fn hide(&self) {}
+
+    pub fn set_window_title(&self, title: &str) {
+        let c_title = CString::new(title).unwrap_or_default();
+        unsafe {
+            wl_proxy_marshal_flags(
+                self.xdg_toplevel,
+                XDG_TOPLEVEL_SET_TITLE,
+                null(),
+                wl_proxy_get_version(self.xdg_toplevel),
+                0,
+                c_title.as_ptr(),
+            );
+            wl_display_flush(self.connection.display);
+        }
+    }
+
+    /// Decorations are drawn by the compositor, if at all.
+    pub fn set_window_border_width(&self, _width: u32) {}
+
+    pub fn get_window_size(&self) -> (u32, u32) {
+        self.state.physical_size()
+    }
+
+    /// Wayland doesn't tell clients where their windows are.
+    pub fn get_window_pos(&self) -> (u32, u32) {
+        (0, 0)
+    }
+
+    /// Decorations are the compositor's decision in xdg-shell.
+    pub fn set_undecorated(&self, _b: bool) {}
+
+    pub fn set_maximized(&self, maximized: bool) {
+        let opcode = if maximized {
+            XDG_TOPLEVEL_SET_MAXIMIZED
+        } else {
+            XDG_TOPLEVEL_UNSET_MAXIMIZED
+        };
+        unsafe {
+            wl_proxy_marshal_flags(
+                self.xdg_toplevel,
+                opcode,
+                null(),
+                wl_proxy_get_version(self.xdg_toplevel),
+                0,
+            );
+            wl_display_flush(self.connection.display);
+        }
+    }
+
+    /// xdg-shell has no request to restore a minimized toplevel, so only `true` has an effect.
+    pub fn set_minimized(&self, minimized: bool) {
+        if !minimized {
+            return;
+        }
+        unsafe {
+            wl_proxy_marshal_flags(
+                self.xdg_toplevel,
+                XDG_TOPLEVEL_SET_MINIMIZED,
+                null(),
+                wl_proxy_get_version(self.xdg_toplevel),
+                0,
+            );
+            wl_display_flush(self.connection.display);
+        }
+    }
+
+    pub fn keyboard_state(&self) -> KeyboardState {
+        self.state.keyboard.borrow().clone()
+    }
+
+    /// Input methods need the text-input protocol, which isn't bound.
+    pub fn set_ime_enabled(&self, _enabled: bool) {}
+
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {}
+
+    pub fn quit(&self) {
+        self.running.set(false);
+    }
+
+    fn commit(&self) {
+        unsafe {
+            wl_proxy_marshal_flags(
+                self.surface,
+                WL_SURFACE_COMMIT,
+                null(),
+                wl_proxy_get_version(self.surface),
+                0,
+            );
+            wl_display_flush(self.connection.display);
+        }
+    }
+}
+
+impl Drop for RWindow {
+    fn drop(&mut self) {
+        let globals = &self.connection.globals;
+        let state: *const State = &*self.state;
+        globals.windows.borrow_mut().retain(|&window| window != state);
+        for focus in [&globals.pointer_focus, &globals.keyboard_focus] {
+            if focus.get() == self.surface {
+                focus.set(null_mut());
+            }
+        }
+        if globals.repeat.get().is_some_and(|repeat| repeat.surface == self.surface) {
+            globals.repeat.set(None);
+        }
+
+        unsafe {
+            let egl = self.connection.egl();
+            if egl::GetCurrentContext() == self.ctx {
+                egl::MakeCurrent(egl.raw, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+            }
+            if self.ctx != egl::NO_CONTEXT {
+                egl::DestroyContext(egl.raw, self.ctx);
+            }
+            if self.egl_surface != egl::NO_SURFACE {
+                egl::DestroySurface(egl.raw, self.egl_surface);
+            }
+            let egl_window = self.state.egl_window.get();
+            if !egl_window.is_null() {
+                wl_egl_window_destroy(egl_window);
+            }
+            for (proxy, opcode) in [
+                (self.xdg_toplevel, XDG_TOPLEVEL_DESTROY),
+                (self.xdg_surface, XDG_SURFACE_DESTROY),
+                (self.surface, WL_SURFACE_DESTROY),
+            ] {
+                if !proxy.is_null() {
+                    wl_proxy_marshal_flags(
+                        proxy,
+                        opcode,
+                        null(),
+                        wl_proxy_get_version(proxy),
+                        WL_MARSHAL_FLAG_DESTROY,
+                    );
+                }
+            }
+            wl_display_flush(self.connection.display);
+        }
+
+        // The surface's address may be handed to the next window, which mustn't get these.
+        let id = self.id();
+        self.connection.events.borrow_mut().retain(|(event_id, _)| *event_id != id);
+    }
+}
+
+static REGISTRY_LISTENER: wl_registry_listener = wl_registry_listener {
+    global: registry_global,
+    global_remove: registry_global_remove,
+};
+
+static WM_BASE_LISTENER: xdg_wm_base_listener = xdg_wm_base_listener { ping: wm_base_ping };
+
+static XDG_SURFACE_LISTENER: xdg_surface_listener = xdg_surface_listener {
+    configure: xdg_surface_configure,
+};
+
+static XDG_TOPLEVEL_LISTENER: xdg_toplevel_listener = xdg_toplevel_listener {
+    configure: xdg_toplevel_configure,
+    close: xdg_toplevel_close,
+};
+
+static SURFACE_LISTENER: wl_surface_listener = wl_surface_listener {
+    enter: surface_enter,
+    leave: surface_leave,
+};
+
+static OUTPUT_LISTENER: wl_output_listener = wl_output_listener {
+    geometry: output_geometry,
+    mode: output_mode,
+    done: output_done,
+    scale: output_scale,
+};
+
+static SEAT_LISTENER: wl_seat_listener = wl_seat_listener {
+    capabilities: seat_capabilities,
+    name: seat_name,
+};
+
+static POINTER_LISTENER: wl_pointer_listener = wl_pointer_listener {
+    enter: pointer_enter,
+    leave: pointer_leave,
+    motion: pointer_motion,
+    button: pointer_button,
+    axis: pointer_axis,
+    frame: pointer_frame,
+    axis_source: pointer_axis_source,
+    axis_stop: pointer_axis_stop,
+    axis_discrete: pointer_axis_discrete,
+};
+
+static KEYBOARD_LISTENER: wl_keyboard_listener = wl_keyboard_listener {
+    keymap: keyboard_keymap,
+    enter: keyboard_enter,
+    leave: keyboard_leave,
+    key: keyboard_key,
+    modifiers: keyboard_modifiers,
+    repeat_info: keyboard_repeat_info,
+};
+
+unsafe extern "C" fn registry_global(
+    data: *mut c_void,
+    registry: *mut wl_proxy,
+    name: u32,
+    interface: *const c_char,
+    version: u32,
+) {
+    let globals = &*(data as *const Globals);
+    let bind = |interface: *const wl_interface, version: u32| {
+        wl_proxy_marshal_flags(
+            registry,
+            WL_REGISTRY_BIND,
+            interface,
+            version,
+            0,
+            name,
+            (*interface).name,
+            version,
+            null_mut::<c_void>(),
+        )
+    };
+
+    match CStr::from_ptr(interface).to_bytes() {
+        b"wl_compositor" if globals.compositor.get().is_null() => {
+            globals
+                .compositor
+                .set(bind(addr_of!(wl_compositor_interface), version.min(4)));
+        }
+        b"xdg_wm_base" if globals.wm_base.get().is_null() => {
+            globals.wm_base.set(bind(addr_of!(xdg_wm_base_interface), 1));
+        }
+        // Only the first seat is used, which is all most compositors have.
+        b"wl_seat" if globals.seat.get().is_null() => {
+            let seat = bind(addr_of!(wl_seat_interface), version.min(5));
+            wl_proxy_add_listener(seat, addr_of!(SEAT_LISTENER) as *const c_void, data);
+            globals.seat.set(seat);
+        }
+        b"wl_output" => {
+            let proxy = bind(addr_of!(wl_output_interface), version.min(2));
+            wl_proxy_add_listener(proxy, addr_of!(OUTPUT_LISTENER) as *const c_void, data);
+            globals.outputs.borrow_mut().push(Output {
+                proxy,
+                name,
+                scale: Cell::new(1),
+            });
+        }
+        _ => {}
+    }
+}
+
+unsafe extern "C" fn registry_global_remove(data: *mut c_void, _registry: *mut wl_proxy, name: u32) {
+    let globals = &*(data as *const Globals);
+    let mut outputs = globals.outputs.borrow_mut();
+    let Some(index) = outputs.iter().position(|output| output.name == name) else {
+        return;
+    };
+    let output = outputs.remove(index);
+    drop(outputs);
+
+    for &window in globals.windows.borrow().iter() {
+        (*window).outputs.borrow_mut().retain(|&proxy| proxy != output.proxy);
+        (*window).update_scale();
+    }
+    wl_proxy_destroy(output.proxy);
+}
+
+unsafe extern "C" fn wm_base_ping(_data: *mut c_void, wm_base: *mut wl_proxy, serial: u32) {
+    wl_proxy_marshal_flags(
+        wm_base,
+        XDG_WM_BASE_PONG,
+        null(),
+        wl_proxy_get_version(wm_base),
+        0,
+        serial,
+    );
+}
+
+unsafe extern "C" fn xdg_surface_configure(data: *mut c_void, xdg_surface: *mut wl_proxy, serial: u32) {
+    let state = &*(data as *const State);
+
+    wl_proxy_marshal_flags(
+        xdg_surface,
+        XDG_SURFACE_ACK_CONFIGURE,
+        null(),
+        wl_proxy_get_version(xdg_surface),
+        0,
+        serial,
+    );
+
+    if let Some(size) = state.pending_size.take() {
+        let resized = state.size.replace(size) != size;
+        let (width, height) = state.physical_size();
+        let egl_window = state.egl_window.get();
+        if !egl_window.is_null() {
+            wl_egl_window_resize(egl_window, width as c_int, height as c_int, 0, 0);
+        }
+        if resized {
+            state.push_event(WindowEvent::Resized(width, height));
+        }
+    }
+
+    state.configured.set(true);
+    state.push_event(WindowEvent::RedrawRequested);
+}
+
+unsafe extern "C" fn xdg_toplevel_configure(
+    data: *mut c_void,
+    _xdg_toplevel: *mut wl_proxy,
+    width: i32,
+    height: i32,
+    states: *mut wl_array,
+) {
+    let state = &*(data as *const State);
+    // 0 leaves the size to the client.
+    if width > 0 && height > 0 {
+        state.pending_size.set(Some((width as u32, height as u32)));
+    }
+
+    // An empty array may have no storage at all.
+    let focused = !(*states).data.is_null()
+        && std::slice::from_raw_parts((*states).data as *const u32, (*states).size / 4)
+            .contains(&XDG_TOPLEVEL_STATE_ACTIVATED);
+    if state.focused.replace(focused) != focused {
+        state.push_event(WindowEvent::Focused(focused));
+    }
+}
+
+unsafe extern "C" fn xdg_toplevel_close(data: *mut c_void, _xdg_toplevel: *mut wl_proxy) {
+    let state = &*(data as *const State);
+    state.push_event(WindowEvent::CloseRequested);
+}
+
+unsafe extern "C" fn surface_enter(data: *mut c_void, _surface: *mut wl_proxy, output: *mut wl_proxy) {
+    let state = &*(data as *const State);
+    state.outputs.borrow_mut().push(output);
+    state.update_scale();
+}
+
+unsafe extern "C" fn surface_leave(data: *mut c_void, _surface: *mut wl_proxy, output: *mut wl_proxy) {
+    let state = &*(data as *const State);
+    state.outputs.borrow_mut().retain(|&proxy| proxy != output);
+    state.update_scale();
+}
+
+unsafe extern "C" fn output_geometry(
+    _data: *mut c_void,
+    _output: *mut wl_proxy,
+    _x: i32,
+    _y: i32,
+    _physical_width: i32,
+    _physical_height: i32,
+    _subpixel: i32,
+    _make: *const c_char,
+    _model: *const c_char,
+    _transform: i32,
+) {
+}
+
+unsafe extern "C" fn output_mode(
+    _data: *mut c_void,
+    _output: *mut wl_proxy,
+    _flags: u32,
+    _width: i32,
+    _height: i32,
+    _refresh: i32,
+) {
+}
+
+unsafe extern "C" fn output_scale(data: *mut c_void, output: *mut wl_proxy, factor: i32) {
+    let globals = &*(data as *const Globals);
+    if let Some(output) = globals.outputs.borrow().iter().find(|known| known.proxy == output) {
+        output.scale.set(factor);
+    }
+}
+
+/// Ends a batch of changes to an output, which is when its scale applies.
+unsafe extern "C" fn output_done(data: *mut c_void, _output: *mut wl_proxy) {
+    let globals = &*(data as *const Globals);
+    for &window in globals.windows.borrow().iter() {
+        (*window).update_scale();
+    }
+}
+
+/// Devices that don't come with version 3 can only be forgotten about.
+unsafe fn release(proxy: *mut wl_proxy, opcode: u32, since: u32) {
+    let version = wl_proxy_get_version(proxy);
+    if version >= since {
+        wl_proxy_marshal_flags(proxy, opcode, null(), version, WL_MARSHAL_FLAG_DESTROY);
+    } else {
+        wl_proxy_destroy(proxy);
+    }
+}
+
+unsafe extern "C" fn seat_capabilities(data: *mut c_void, seat: *mut wl_proxy, capabilities: u32) {
+    let globals = &*(data as *const Globals);
+    let get_device = |opcode: u32, interface: *const wl_interface, listener: *const c_void| {
+        let device = wl_proxy_marshal_flags(
+            seat,
+            opcode,
+            interface,
+            wl_proxy_get_version(seat),
+            0,
+            null_mut::<c_void>(),
+        );
+        wl_proxy_add_listener(device, listener, data);
+        device
+    };
+
+    let pointer = globals.pointer.get();
+    if capabilities & WL_SEAT_CAPABILITY_POINTER != 0 && pointer.is_null() {
+        globals.pointer.set(get_device(
+            WL_SEAT_GET_POINTER,
+            addr_of!(wl_pointer_interface),
+            addr_of!(POINTER_LISTENER) as *const c_void,
+        ));
+    } else if capabilities & WL_SEAT_CAPABILITY_POINTER == 0 && !pointer.is_null() {
+        release(pointer, WL_POINTER_RELEASE, 3);
+        globals.pointer.set(null_mut());
+        globals.pointer_focus.set(null_mut());
+    }
+
+    let keyboard = globals.keyboard.get();
+    if capabilities & WL_SEAT_CAPABILITY_KEYBOARD != 0 && keyboard.is_null() {
+        globals.keyboard.set(get_device(
+            WL_SEAT_GET_KEYBOARD,
+            addr_of!(wl_keyboard_interface),
+            addr_of!(KEYBOARD_LISTENER) as *const c_void,
+        ));
+    } else if capabilities & WL_SEAT_CAPABILITY_KEYBOARD == 0 && !keyboard.is_null() {
+        release(keyboard, WL_KEYBOARD_RELEASE, 3);
+        globals.keyboard.set(null_mut());
+        if let Some(state) = window_state(globals.keyboard_focus.replace(null_mut())) {
+            state.keyboard.borrow_mut().clear();
+        }
+        globals.repeat.set(None);
+    }
+}
+
+unsafe extern "C" fn seat_name(_data: *mut c_void, _seat: *mut wl_proxy, _name: *const c_char) {}
+
+unsafe extern "C" fn pointer_enter(
+    data: *mut c_void,
+    _pointer: *mut wl_proxy,
+    _serial: u32,
+    surface: *mut wl_proxy,
+    x: wl_fixed_t,
+    y: wl_fixed_t,
+) {
+    let globals = &*(data as *const Globals);
+    globals.pointer_focus.set(surface);
+    if let Some(state) = window_state(surface) {
+        state.push_event(WindowEvent::CursorEntered);
+        state.push_cursor_moved(x, y);
+    }
+}
+
+unsafe extern "C" fn pointer_leave(data: *mut c_void, _pointer: *mut wl_proxy, _serial: u32, surface: *mut wl_proxy) {
+    let globals = &*(data as *const Globals);
+    globals.pointer_focus.set(null_mut());
+    globals.axis.take();
+    if let Some(state) = window_state(surface) {
+        state.push_event(WindowEvent::CursorLeft);
+    }
+}
+
+unsafe extern "C" fn pointer_motion(data: *mut c_void, _pointer: *mut wl_proxy, _time: u32, x: wl_fixed_t, y: wl_fixed_t) {
+    let globals = &*(data as *const Globals);
+    if let Some(state) = window_state(globals.pointer_focus.get()) {
+        state.push_cursor_moved(x, y);
+    }
+}
+
+unsafe extern "C" fn pointer_button(
+    data: *mut c_void,
+    _pointer: *mut wl_proxy,
+    _serial: u32,
+    _time: u32,
+    button: u32,
+    button_state: u32,
+) {
+    let globals = &*(data as *const Globals);
+    let Some(state) = window_state(globals.pointer_focus.get()) else {
+        return;
+    };
+    // Linux input event codes, from BTN_LEFT.
+    let button = match button {
+        0x110 => MouseButton::Left,
+        0x111 => MouseButton::Right,
+        0x112 => MouseButton::Middle,
+        0x113 => MouseButton::Back,
+        0x114 => MouseButton::Forward,
+        other => MouseButton::Other(other as u16),
+    };
+    if button_state == WL_POINTER_BUTTON_STATE_PRESSED {
+        state.push_event(WindowEvent::MouseButtonDown(button));
+    } else {
+        state.push_event(WindowEvent::MouseButtonUp(button));
+    }
+}
+
+unsafe extern "C" fn pointer_axis(data: *mut c_void, pointer: *mut wl_proxy, _time: u32, axis: u32, value: wl_fixed_t) {
+    let globals = &*(data as *const Globals);
+    let ((mut x, mut y), discrete) = globals.axis.get();
+    if axis == WL_POINTER_AXIS_VERTICAL_SCROLL {
+        y += wl_fixed_to_double(value);
+    } else {
+        x += wl_fixed_to_double(value);
+    }
+    globals.axis.set(((x, y), discrete));
+    // Before version 5 there are no frames to wait for.
+    if wl_proxy_get_version(pointer) < 5 {
+        pointer_frame(data, pointer);
+    }
+}
+
+/// Ends a batch of pointer events, which is when the scrolling they add up to is reported.
+unsafe extern "C" fn pointer_frame(data: *mut c_void, _pointer: *mut wl_proxy) {
+    let globals = &*(data as *const Globals);
+    let ((x, y), (discrete_x, discrete_y)) = globals.axis.take();
+    let Some(state) = window_state(globals.pointer_focus.get()) else {
+        return;
+    };
+    // Wheels also report notches, touchpads only distances. Wayland's vertical axis grows downwards.
+    let delta = if discrete_x != 0 || discrete_y != 0 {
+        ScrollDelta::Lines {
+            x: discrete_x as f32,
+            y: -discrete_y as f32,
+        }
+    } else if x != 0.0 || y != 0.0 {
+        let scale = state.scale.get() as f64;
+        ScrollDelta::Pixels {
+            x: x * scale,
+            y: -y * scale,
+        }
+    } else {
+        return;
+    };
+    state.push_event(WindowEvent::MouseWheel(delta));
+}
+
+unsafe extern "C" fn pointer_axis_source(_data: *mut c_void, _pointer: *mut wl_proxy, _source: u32) {}
+
+unsafe extern "C" fn pointer_axis_stop(_data: *mut c_void, _pointer: *mut wl_proxy, _time: u32, _axis: u32) {}
+
+unsafe extern "C" fn pointer_axis_discrete(data: *mut c_void, _pointer: *mut wl_proxy, axis: u32, discrete: i32) {
+    let globals = &*(data as *const Globals);
+    let (pixels, (mut x, mut y)) = globals.axis.get();
+    if axis == WL_POINTER_AXIS_VERTICAL_SCROLL {
+        y += discrete;
+    } else {
+        x += discrete;
+    }
+    globals.axis.set((pixels, (x, y)));
+}
+
+unsafe extern "C" fn keyboard_keymap(data: *mut c_void, _keyboard: *mut wl_proxy, format: u32, fd: c_int, size: u32) {
+    let globals = &*(data as *const Globals);
+    // Closed when dropped.
+    let file = File::from_raw_fd(fd);
+    if format != WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1 {
+        return;
+    }
+    let mut keymap = vec![0u8; size as usize];
+    if file.read_exact_at(&mut keymap, 0).is_err() {
+        return;
+    }
+    if let Ok(keymap) = CStr::from_bytes_until_nul(&keymap) {
+        *globals.xkb.borrow_mut() = Xkb::new(keymap);
+    }
+}
+
+unsafe extern "C" fn keyboard_enter(
+    data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _serial: u32,
+    surface: *mut wl_proxy,
+    _keys: *mut wl_array,
+) {
+    let globals = &*(data as *const Globals);
+    globals.keyboard_focus.set(surface);
+}
+
+unsafe extern "C" fn keyboard_leave(data: *mut c_void, _keyboard: *mut wl_proxy, _serial: u32, surface: *mut wl_proxy) {
+    let globals = &*(data as *const Globals);
+    globals.keyboard_focus.set(null_mut());
+    globals.repeat.set(None);
+    if let Some(state) = window_state(surface) {
+        state.keyboard.borrow_mut().clear();
+    }
+}
+
+unsafe extern "C" fn keyboard_key(
+    data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _serial: u32,
+    _time: u32,
+    key: u32,
+    key_state: u32,
+) {
+    let globals = &*(data as *const Globals);
+    let surface = globals.keyboard_focus.get();
+    let Some(state) = window_state(surface) else {
+        return;
+    };
+    // XKB keycodes are evdev codes plus 8, as on X11.
+    let keycode = key + 8;
+    let pressed = key_state == WL_KEYBOARD_KEY_STATE_PRESSED;
+    globals.key_event(state, keycode, pressed);
+
+    if pressed {
+        let repeats = globals.xkb.borrow().as_ref().is_some_and(|xkb| xkb.repeats(keycode));
+        let (rate, delay) = globals.repeat_info.get();
+        if repeats && rate > 0 {
+            globals.repeat.set(Some(Repeat {
+                surface,
+                keycode,
+                next: Instant::now() + Duration::from_millis(delay.max(0) as u64),
+            }));
+        }
+    } else if globals.repeat.get().is_some_and(|repeat| repeat.keycode == keycode) {
+        globals.repeat.set(None);
+    }
+}
+
+unsafe extern "C" fn keyboard_modifiers(
+    data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _serial: u32,
+    depressed: u32,
+    latched: u32,
+    locked: u32,
+    group: u32,
+) {
+    let globals = &*(data as *const Globals);
+    if let Some(xkb) = globals.xkb.borrow().as_ref() {
+        xkb_state_update_mask(xkb.state, depressed, latched, locked, 0, 0, group);
+    }
+}
+
+unsafe extern "C" fn keyboard_repeat_info(data: *mut c_void, _keyboard: *mut wl_proxy, rate: i32, delay: i32) {
+    let globals = &*(data as *const Globals);
+    globals.repeat_info.set((rate, delay));
+}
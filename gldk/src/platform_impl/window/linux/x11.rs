@@ -0,0 +1,1845 @@
+use crate::context::ContextInfo;
+use crate::error::{ErrorKind, GLDKError};
+use crate::platform_impl::egl::{self as egl_impl, EGLConnection};
+use crate::sys::egl::types::{EGLConfig, EGLContext, EGLSurface};
+use crate::sys::*;
+use crate::window::{
+    ControlFlow, KeyboardInput, KeyboardState, Modifiers, MouseButton, Position, ScrollDelta, VirtualKeyCode, WindowEvent, WindowID,
+};
+use crate::{GLConfig, GLContextBackend, GLFramebufferFormat, GLProfile, GLResetNotification, GLVersion};
+use core::ffi::c_void;
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle, XlibDisplayHandle, XlibWindowHandle};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{CStr, CString};
+use std::mem::MaybeUninit;
+use std::os::raw::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, c_ushort};
+use std::ptr::{addr_of, null, null_mut};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use x11::glx::*;
+use x11::keysym::*;
+use x11::xinput2::*;
+use x11::xlib::*;
+
+const _NET_WM_STATE_REMOVE: c_long = 0;
+const _NET_WM_STATE_ADD: c_long = 1;
+/// `WM_STATE` of an iconified window, from ICCCM.
+const ICONIC_STATE: c_long = 3;
+
+type XIMStyle = c_ulong;
+
+/// Xlib's `XIMStyles`, which the x11 crate doesn't bind.
+#[repr(C)]
+struct XIMStyles {
+    count_styles: c_ushort,
+    supported_styles: *mut XIMStyle,
+}
+
+/// What the connection remembers of each window, to only report what actually changed.
+struct WindowState {
+    size: (u32, u32),
+    position: Option<(i32, i32)>,
+    minimized: bool,
+    keyboard: KeyboardState,
+    /// Null when the connection has no input method, or it can't be turned off for this window.
+    ic: XIC,
+    ime_enabled: bool,
+    /// Where the candidate window goes, kept to carry it over to a new input context.
+    spot: XPoint,
+    preedit: Rc<Preedit>,
+}
+
+/// The text the input method is composing in a window, as its preedit callbacks describe it.
+struct Preedit {
+    window: Window,
+    events: Rc<RefCell<VecDeque<(WindowID, WindowEvent)>>>,
+    text: RefCell<Vec<char>>,
+    /// Index of the caret in `text`, `None` while it is hidden.
+    caret: Cell<Option<usize>>,
+}
+
+impl Preedit {
+    fn emit(&self) {
+        let text = self.text.borrow();
+        let cursor = self
+            .caret
+            .get()
+            .map(|caret| text[..caret.min(text.len())].iter().map(|c| c.len_utf8()).sum());
+        let event = WindowEvent::ImePreedit {
+            text: text.iter().collect(),
+            cursor,
+        };
+        self.events.borrow_mut().push_back((WindowID(self.window), event));
+    }
+
+    /// Forgets the composition, returning whether there was one.
+    fn clear(&self) -> bool {
+        self.caret.set(None);
+        !std::mem::take(&mut *self.text.borrow_mut()).is_empty()
+    }
+}
+
+/// A valuator that scrolls, as XInput 2.1 describes it.
+struct ScrollValuator {
+    number: c_int,
+    vertical: bool,
+    /// How far the valuator moves for one wheel notch.
+    increment: f64,
+    /// Where the valuator last was, which the next motion is relative to.
+    position: f64,
+}
+
+/// An Xlib connection shared by every window created from it, closed with the last one.
+pub struct XConnection {
+    display: *mut Display,
+    /// Null when the user's input method can't be opened.
+    im: XIM,
+    /// The input styles `im` supports.
+    im_styles: Vec<XIMStyle>,
+    /// Opened by the first EGL window and shared by the rest, terminated with the connection.
+    egl: OnceCell<EGLConnection>,
+    wm_delete_window: Atom,
+    wm_state: Atom,
+    scale_factor: f64,
+    /// The major opcode of XInput, `None` unless the server has version 2.1, the first with
+    /// scroll valuators.
+    xi_opcode: Option<c_int>,
+    /// The scroll valuators of every device that has some, by device ID.
+    scroll_devices: RefCell<HashMap<c_int, Vec<ScrollValuator>>>,
+    windows: RefCell<HashMap<Window, WindowState>>,
+    /// Events of every window on this connection, as translated from the server's. Shared with
+    /// the preedit callbacks, which Xlib calls from `XFilterEvent`.
+    events: Rc<RefCell<VecDeque<(WindowID, WindowEvent)>>>,
+}
+
+impl XConnection {
+    pub fn open() -> Result<Rc<Self>, GLDKError> {
+        let display = unsafe { XOpenDisplay(null_mut()) };
+        if display.is_null() {
+            return Err(GLDKError::new_unexpected(
+                "Can't open X display.".to_owned(),
+            ));
+        }
+        // Held keys then repeat presses only, instead of pairs of releases and presses.
+        unsafe { XkbSetDetectableAutoRepeat(display, True, null_mut()) };
+        let im = unsafe { open_input_method(display) };
+        let connection = Rc::new(Self {
+            display,
+            im,
+            im_styles: unsafe { input_styles(im) },
+            egl: OnceCell::new(),
+            wm_delete_window: intern_atom(display, "WM_DELETE_WINDOW"),
+            wm_state: intern_atom(display, "WM_STATE"),
+            scale_factor: xft_dpi(display).map_or(1.0, |dpi| dpi / 96.0),
+            xi_opcode: unsafe { query_xinput(display) },
+            scroll_devices: RefCell::new(HashMap::new()),
+            windows: RefCell::new(HashMap::new()),
+            events: Rc::new(RefCell::new(VecDeque::new())),
+        });
+        connection.refresh_scroll_devices();
+        Ok(connection)
+    }
+
+    /// Returns the EGL display of this connection, initializing it on first use.
+    fn open_egl(&self) -> Result<&EGLConnection, GLDKError> {
+        if let Some(egl) = self.egl.get() {
+            return Ok(egl);
+        }
+        let egl = unsafe {
+            EGLConnection::open(
+                egl::PLATFORM_X11_KHR,
+                "EGL_KHR_platform_x11",
+                self.display as *mut c_void,
+            )?
+        };
+        Ok(self.egl.get_or_init(|| egl))
+    }
+
+    /// Only valid once an EGL window has been created.
+    fn egl(&self) -> &EGLConnection {
+        self.egl.get().unwrap()
+    }
+
+    /// Returns the next queued event, blocking until the server sends something if there is none,
+    /// for at most `timeout` unless it is `None`.
+    pub fn wait_event(&self, timeout: Option<Duration>) -> Result<Option<(WindowID, WindowEvent)>, GLDKError> {
+        if let Some(event) = self.events.borrow_mut().pop_front() {
+            return Ok(Some(event));
+        }
+        // XPending flushes the requests and reads whatever the server already sent.
+        if let Some(timeout) = timeout {
+            let pending = || unsafe { XPending(self.display) } > 0;
+            let fd = unsafe { XConnectionNumber(self.display) };
+            let ready = pending() || (super::wait_readable(fd, timeout) && pending());
+            if !ready {
+                return Ok(None);
+            }
+        }
+        let mut event = unsafe {
+            let mut event = MaybeUninit::<XEvent>::uninit();
+            XNextEvent(self.display, event.as_mut_ptr());
+            event.assume_init()
+        };
+        // Events the input method consumes, such as dead keys, are only for it to see.
+        if unsafe { XFilterEvent(&mut event, 0) } != 0 {
+            return Ok(None);
+        }
+        self.translate(event);
+        Ok(self.events.borrow_mut().pop_front())
+    }
+
+    fn register(&self, window: Window, width: u32, height: u32) {
+        let mut state = WindowState {
+            size: (width, height),
+            position: None,
+            minimized: false,
+            keyboard: KeyboardState::default(),
+            ic: null_mut(),
+            ime_enabled: true,
+            spot: XPoint { x: 0, y: 0 },
+            preedit: Rc::new(Preedit {
+                window,
+                events: self.events.clone(),
+                text: RefCell::new(Vec::new()),
+                caret: Cell::new(None),
+            }),
+        };
+        state.ic = self.create_input_context(window, &state);
+        self.windows.borrow_mut().insert(window, state);
+    }
+
+    /// Composing in place if the input method lets GLDK report the preedit, in a window of its
+    /// own otherwise. Disabled windows get a context that doesn't compose at all, so they still
+    /// get text in the layout's script.
+    fn input_style(&self, enabled: bool) -> Option<XIMStyle> {
+        let candidates: &[c_int] = if enabled {
+            &[XIMPreeditCallbacks | XIMStatusNothing, XIMPreeditNothing | XIMStatusNothing]
+        } else {
+            &[XIMPreeditNone | XIMStatusNone]
+        };
+        candidates
+            .iter()
+            .map(|&style| style as XIMStyle)
+            .find(|style| self.im_styles.contains(style))
+    }
+
+    fn create_input_context(&self, window: Window, state: &WindowState) -> XIC {
+        let Some(style) = self.input_style(state.ime_enabled) else {
+            return null_mut();
+        };
+        unsafe {
+            if style & XIMPreeditCallbacks as XIMStyle == 0 {
+                return XCreateIC(
+                    self.im,
+                    XNInputStyle_0.as_ptr(),
+                    style,
+                    XNClientWindow_0.as_ptr(),
+                    window,
+                    XNFocusWindow_0.as_ptr(),
+                    window,
+                    null_mut::<c_void>(),
+                );
+            }
+
+            // Xlib copies the callbacks and the spot, the preedit itself lives as long as the window.
+            let client_data = Rc::as_ptr(&state.preedit) as XPointer;
+            let callback = |callback| XICCallback {
+                client_data,
+                callback: Some(callback),
+            };
+            let (start, done) = (callback(preedit_start), callback(preedit_done));
+            let (draw, caret) = (callback(preedit_draw), callback(preedit_caret));
+            let attributes = XVaCreateNestedList(
+                0,
+                XNSpotLocation_0.as_ptr(),
+                &state.spot,
+                XNPreeditStartCallback_0.as_ptr(),
+                &start,
+                XNPreeditDoneCallback_0.as_ptr(),
+                &done,
+                XNPreeditDrawCallback_0.as_ptr(),
+                &draw,
+                XNPreeditCaretCallback_0.as_ptr(),
+                &caret,
+                null_mut::<c_void>(),
+            );
+            let ic = XCreateIC(
+                self.im,
+                XNInputStyle_0.as_ptr(),
+                style,
+                XNClientWindow_0.as_ptr(),
+                window,
+                XNFocusWindow_0.as_ptr(),
+                window,
+                XNPreeditAttributes_0.as_ptr(),
+                attributes,
+                null_mut::<c_void>(),
+            );
+            XFree(attributes);
+            ic
+        }
+    }
+
+    fn set_ime_enabled(&self, window: Window, enabled: bool) {
+        let mut windows = self.windows.borrow_mut();
+        let Some(state) = windows.get_mut(&window) else {
+            return;
+        };
+        if state.ime_enabled == enabled || self.im.is_null() {
+            return;
+        }
+        state.ime_enabled = enabled;
+        if !state.ic.is_null() {
+            unsafe { XDestroyIC(state.ic) };
+        }
+        state.ic = self.create_input_context(window, state);
+
+        let (mut focus, mut revert) = (0, 0);
+        unsafe { XGetInputFocus(self.display, &mut focus, &mut revert) };
+        if !state.ic.is_null() && focus == window {
+            unsafe { XSetICFocus(state.ic) };
+        }
+        // The old context goes without ending its composition.
+        let preedit = state.preedit.clone();
+        drop(windows);
+        if preedit.clear() {
+            preedit.emit();
+        }
+    }
+
+    fn set_ime_position(&self, window: Window, x: i32, y: i32) {
+        let mut windows = self.windows.borrow_mut();
+        let Some(state) = windows.get_mut(&window) else {
+            return;
+        };
+        state.spot = XPoint {
+            x: x.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+            y: y.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+        };
+        if state.ic.is_null() || !state.ime_enabled {
+            return;
+        }
+        unsafe {
+            let attributes = XVaCreateNestedList(0, XNSpotLocation_0.as_ptr(), &state.spot, null_mut::<c_void>());
+            XSetICValues(state.ic, XNPreeditAttributes_0.as_ptr(), attributes, null_mut::<c_void>());
+            XFree(attributes);
+            XFlush(self.display);
+        }
+    }
+
+    /// The XID may be handed to the next window, which mustn't get the queued events.
+    fn unregister(&self, window: Window) {
+        if let Some(state) = self.windows.borrow_mut().remove(&window) {
+            if !state.ic.is_null() {
+                unsafe { XDestroyIC(state.ic) };
+            }
+        }
+        self.events
+            .borrow_mut()
+            .retain(|(id, _)| *id != WindowID(window));
+    }
+
+    /// Motions through XInput carry the scroll valuators, and device changes tell when the
+    /// valuators of the pointer change.
+    fn select_xinput(&self, window: Window) {
+        if self.xi_opcode.is_none() {
+            return;
+        }
+        let mut mask = [0u8; 1];
+        XISetMask(&mut mask, XI_Motion);
+        XISetMask(&mut mask, XI_DeviceChanged);
+        let mut event_mask = XIEventMask {
+            deviceid: XIAllMasterDevices,
+            mask_len: mask.len() as c_int,
+            mask: mask.as_mut_ptr(),
+        };
+        unsafe { XISelectEvents(self.display, window, &mut event_mask, 1) };
+    }
+
+    /// Reads the scroll valuators again, along with where they are now, which the next motions are
+    /// relative to. Valuators keep moving while the pointer is over other windows.
+    fn refresh_scroll_devices(&self) {
+        if self.xi_opcode.is_none() {
+            return;
+        }
+        let mut devices = HashMap::new();
+        unsafe {
+            let mut count = 0;
+            let info = XIQueryDevice(self.display, XIAllDevices, &mut count);
+            if info.is_null() {
+                return;
+            }
+            for device in std::slice::from_raw_parts(info, count as usize) {
+                let classes = std::slice::from_raw_parts(device.classes, device.num_classes as usize);
+                let mut valuators: Vec<ScrollValuator> = classes
+                    .iter()
+                    .filter(|&&class| (*class)._type == XIScrollClass)
+                    .map(|&class| {
+                        let scroll = &*(class as *const XIScrollClassInfo);
+                        ScrollValuator {
+                            number: scroll.number,
+                            vertical: scroll.scroll_type == XIScrollTypeVertical,
+                            increment: scroll.increment,
+                            position: 0.0,
+                        }
+                    })
+                    .collect();
+                if valuators.is_empty() {
+                    continue;
+                }
+                for &class in classes.iter().filter(|&&class| (*class)._type == XIValuatorClass) {
+                    let valuator = &*(class as *const XIValuatorClassInfo);
+                    if let Some(scroll) = valuators.iter_mut().find(|scroll| scroll.number == valuator.number) {
+                        scroll.position = valuator.value;
+                    }
+                }
+                devices.insert(device.deviceid, valuators);
+            }
+            XIFreeDeviceInfo(info);
+        }
+        *self.scroll_devices.borrow_mut() = devices;
+    }
+
+    /// Whether wheels and touchpads report through scroll valuators, which servers then also
+    /// emulate buttons 4 to 7 for. Devices without valuators are rare enough since XInput 2.1 that
+    /// their buttons are ignored too.
+    fn smooth_scrolling(&self) -> bool {
+        !self.scroll_devices.borrow().is_empty()
+    }
+
+    #[allow(non_upper_case_globals)]
+    fn translate_xinput(&self, mut event: XEvent) {
+        let cookie = unsafe { &mut event.generic_event_cookie };
+        if Some(cookie.extension) != self.xi_opcode || unsafe { XGetEventData(self.display, cookie) } == False {
+            return;
+        }
+        match cookie.evtype {
+            XI_Motion => self.xinput_motion(unsafe { &*(cookie.data as *const XIDeviceEvent) }),
+            XI_DeviceChanged => self.refresh_scroll_devices(),
+            _ => {}
+        }
+        unsafe { XFreeEventData(self.display, cookie) };
+    }
+
+    /// Selecting XInput motions stops the core ones, so this reports the cursor too.
+    fn xinput_motion(&self, motion: &XIDeviceEvent) {
+        let push = |event| self.events.borrow_mut().push_back((WindowID(motion.event), event));
+        let mask = unsafe { std::slice::from_raw_parts(motion.valuators.mask, motion.valuators.mask_len as usize) };
+        let mut values = motion.valuators.values;
+
+        let mut moved = false;
+        let (mut x, mut y) = (0.0, 0.0);
+        let mut devices = self.scroll_devices.borrow_mut();
+        let mut scroll_valuators = devices.get_mut(&motion.sourceid);
+        // Only the valuators that changed are sent, in order.
+        for number in 0..(mask.len() * 8) as c_int {
+            if !XIMaskIsSet(mask, number) {
+                continue;
+            }
+            let value = unsafe { *values };
+            values = unsafe { values.add(1) };
+            // The first two are the pointer's position.
+            moved |= number < 2;
+
+            let Some(scroll) = scroll_valuators
+                .as_mut()
+                .and_then(|valuators| valuators.iter_mut().find(|scroll| scroll.number == number))
+            else {
+                continue;
+            };
+            let delta = value - std::mem::replace(&mut scroll.position, value);
+            if scroll.increment == 0.0 {
+                continue;
+            }
+            // Vertical valuators grow downwards.
+            if scroll.vertical {
+                y -= delta / scroll.increment;
+            } else {
+                x += delta / scroll.increment;
+            }
+        }
+        drop(devices);
+
+        if moved {
+            let physical = Position {
+                x: motion.event_x,
+                y: motion.event_y,
+            };
+            push(WindowEvent::CursorMoved {
+                physical,
+                logical: physical.to_logical(self.scale_factor),
+            });
+        }
+        if x != 0.0 || y != 0.0 {
+            push(WindowEvent::MouseWheel(ScrollDelta::Lines {
+                x: x as f32,
+                y: y as f32,
+            }));
+        }
+    }
+
+    #[allow(non_upper_case_globals)]
+    fn translate(&self, mut event: XEvent) {
+        if event.get_type() == GenericEvent {
+            return self.translate_xinput(event);
+        }
+
+        let window = unsafe { event.any.window };
+        let push = |event| self.events.borrow_mut().push_back((WindowID(window), event));
+
+        match event.get_type() {
+            Expose => push(WindowEvent::RedrawRequested),
+            KeyPress => {
+                let key = unsafe { &mut event.key };
+                let text = self.lookup_text(window, key);
+                // Xlib hands over what the input method commits as presses of no key.
+                if key.keycode == 0 {
+                    if !text.is_empty() {
+                        push(WindowEvent::ImeCommit(text));
+                    }
+                    return;
+                }
+                let mut input = keyboard_input(key);
+                if let Some(state) = self.windows.borrow_mut().get_mut(&window) {
+                    input.repeat = state.keyboard.press(&input);
+                }
+                push(WindowEvent::Keydown(input));
+                for c in text.chars() {
+                    push(WindowEvent::ReceivedCharacter(c));
+                }
+            }
+            KeyRelease => {
+                let input = keyboard_input(unsafe { &mut event.key });
+                if let Some(state) = self.windows.borrow_mut().get_mut(&window) {
+                    state.keyboard.release(&input);
+                }
+                push(WindowEvent::Keyup(input))
+            }
+            MotionNotify => {
+                let motion = XMotionEvent::from(event);
+                let physical = Position {
+                    x: motion.x as f64,
+                    y: motion.y as f64,
+                };
+                push(WindowEvent::CursorMoved {
+                    physical,
+                    logical: physical.to_logical(self.scale_factor),
+                })
+            }
+            ButtonPress | ButtonRelease => {
+                let button = XButtonEvent::from(event);
+                let pressed = event.get_type() == ButtonPress;
+                // The core protocol reports each wheel notch as a press of buttons 4 to 7. With
+                // XInput 2.1 these are emulated from the scroll valuators, already reported.
+                let scroll = match button.button {
+                    Button4 => Some((0.0, 1.0)),
+                    Button5 => Some((0.0, -1.0)),
+                    6 => Some((-1.0, 0.0)),
+                    7 => Some((1.0, 0.0)),
+                    _ => None,
+                };
+                if let Some((x, y)) = scroll {
+                    if pressed && !self.smooth_scrolling() {
+                        push(WindowEvent::MouseWheel(ScrollDelta::Lines { x, y }));
+                    }
+                    return;
+                }
+
+                let button = match button.button {
+                    Button1 => MouseButton::Left,
+                    Button2 => MouseButton::Middle,
+                    Button3 => MouseButton::Right,
+                    8 => MouseButton::Back,
+                    9 => MouseButton::Forward,
+                    other => MouseButton::Other(other as u16),
+                };
+                if pressed {
+                    push(WindowEvent::MouseButtonDown(button))
+                } else {
+                    push(WindowEvent::MouseButtonUp(button))
+                }
+            }
+            EnterNotify => {
+                self.refresh_scroll_devices();
+                push(WindowEvent::CursorEntered)
+            }
+            LeaveNotify => push(WindowEvent::CursorLeft),
+            ConfigureNotify => {
+                let configure = XConfigureEvent::from(event);
+                let size = (configure.width as u32, configure.height as u32);
+                // The event's coordinates are relative to the window manager's frame.
+                let position = self.root_position(window);
+
+                let mut windows = self.windows.borrow_mut();
+                let Some(state) = windows.get_mut(&window) else {
+                    return;
+                };
+                let resized = std::mem::replace(&mut state.size, size) != size;
+                let moved = state.position.replace(position) != Some(position);
+                drop(windows);
+
+                if resized {
+                    push(WindowEvent::Resized(size.0, size.1));
+                }
+                if moved {
+                    push(WindowEvent::Moved(position.0, position.1));
+                }
+            }
+            FocusIn | FocusOut => {
+                let focus = XFocusChangeEvent::from(event);
+                // Keyboard grabs, by the window manager's Alt+Tab for example, don't move the focus.
+                if focus.mode != NotifyNormal && focus.mode != NotifyWhileGrabbed {
+                    return;
+                }
+                let focused = focus.type_ == FocusIn;
+                if !focused {
+                    if let Some(state) = self.windows.borrow_mut().get_mut(&window) {
+                        state.keyboard.clear();
+                    }
+                }
+                let ic = self.input_context(window);
+                if !ic.is_null() && focused {
+                    unsafe { XSetICFocus(ic) };
+                } else if !ic.is_null() {
+                    unsafe { XUnsetICFocus(ic) };
+                }
+                push(WindowEvent::Focused(focused))
+            }
+            PropertyNotify => {
+                let property = XPropertyEvent::from(event);
+                if property.atom != self.wm_state {
+                    return;
+                }
+                let minimized = self.is_iconic(window);
+                let changed = self
+                    .windows
+                    .borrow_mut()
+                    .get_mut(&window)
+                    .is_some_and(|state| std::mem::replace(&mut state.minimized, minimized) != minimized);
+                match (changed, minimized) {
+                    (true, true) => push(WindowEvent::Minimized),
+                    (true, false) => push(WindowEvent::Restored),
+                    _ => {}
+                }
+            }
+            ClientMessage => {
+                let message = XClientMessageEvent::from(event);
+                if message.data.get_long(0) as Atom == self.wm_delete_window {
+                    push(WindowEvent::CloseRequested)
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn keyboard_state(&self, window: Window) -> KeyboardState {
+        self.windows
+            .borrow()
+            .get(&window)
+            .map(|state| state.keyboard.clone())
+            .unwrap_or_default()
+    }
+
+    fn input_context(&self, window: Window) -> XIC {
+        self.windows
+            .borrow()
+            .get(&window)
+            .map_or(null_mut(), |state| state.ic)
+    }
+
+    /// The text a key press produced, empty for keys that don't produce any.
+    #[allow(non_upper_case_globals)]
+    fn lookup_text(&self, window: Window, key: &mut XKeyEvent) -> String {
+        let ic = self.input_context(window);
+        let mut buffer = vec![0u8; 32];
+        unsafe {
+            if ic.is_null() {
+                // Without an input method there is only Latin-1.
+                let count = XLookupString(
+                    key,
+                    buffer.as_mut_ptr() as *mut c_char,
+                    buffer.len() as c_int,
+                    null_mut(),
+                    null_mut(),
+                );
+                return buffer[..count.max(0) as usize].iter().map(|&b| b as char).collect();
+            }
+
+            let mut status = 0;
+            let mut count = Xutf8LookupString(
+                ic,
+                key,
+                buffer.as_mut_ptr() as *mut c_char,
+                buffer.len() as c_int,
+                null_mut(),
+                &mut status,
+            );
+            if status == XBufferOverflow {
+                buffer.resize(count as usize, 0);
+                count = Xutf8LookupString(
+                    ic,
+                    key,
+                    buffer.as_mut_ptr() as *mut c_char,
+                    buffer.len() as c_int,
+                    null_mut(),
+                    &mut status,
+                );
+            }
+            match status {
+                XLookupChars | XLookupBoth => String::from_utf8_lossy(&buffer[..count as usize]).into_owned(),
+                _ => String::new(),
+            }
+        }
+    }
+
+    fn root_position(&self, window: Window) -> (i32, i32) {
+        let (mut x, mut y) = (0, 0);
+        let mut child = 0;
+        unsafe {
+            XTranslateCoordinates(
+                self.display,
+                window,
+                XDefaultRootWindow(self.display),
+                0,
+                0,
+                &mut x,
+                &mut y,
+                &mut child,
+            );
+        }
+        (x, y)
+    }
+
+    /// Whether the window manager has iconified the window, per ICCCM's `WM_STATE`.
+    fn is_iconic(&self, window: Window) -> bool {
+        unsafe {
+            let mut actual_type = 0;
+            let mut format = 0;
+            let (mut count, mut remaining) = (0, 0);
+            let mut data = null_mut();
+            XGetWindowProperty(
+                self.display,
+                window,
+                self.wm_state,
+                0,
+                2,
+                False,
+                self.wm_state,
+                &mut actual_type,
+                &mut format,
+                &mut count,
+                &mut remaining,
+                &mut data,
+            );
+            if data.is_null() {
+                return false;
+            }
+            let iconic = count > 0 && *(data as *const c_long) == ICONIC_STATE;
+            XFree(data as *mut _);
+            iconic
+        }
+    }
+}
+
+impl Drop for XConnection {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.im.is_null() {
+                XCloseIM(self.im);
+            }
+            if let Some(egl) = self.egl.get() {
+                egl.terminate();
+            }
+            XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// The context of a window, created through GLX or EGL depending on `GLConfig::backend`.
+enum Context {
+    Glx {
+        glx: GLXARBFunctions,
+        fbconfig: GLXFBConfig,
+        ctx: GLXContext,
+    },
+    Egl {
+        config: EGLConfig,
+        surface: EGLSurface,
+        ctx: EGLContext,
+    },
+}
+
+pub struct Props {
+    connection: Rc<XConnection>,
+    display: *mut Display,
+    screen: c_int,
+    window: Window,
+    visual_id: VisualID,
+    colormap: Colormap,
+    context: Context,
+    version: GLVersion,
+    info: Option<ContextInfo>,
+    format: GLFramebufferFormat,
+}
+
+pub struct RWindow {
+    props: Props,
+    running: Cell<bool>,
+}
+
+impl RWindow {
+    /// `share` has to have been created from the same `connection`.
+    pub fn new(
+        connection: Rc<XConnection>,
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: GLConfig,
+        share: Option<&RWindow>,
+    ) -> Result<Self, GLDKError> {
+        unsafe {
+            let display = connection.display;
+            let screen = XDefaultScreen(display);
+            let root = XRootWindow(display, screen);
+
+            let (context, vi) = match conf.backend {
+                GLContextBackend::Native => {
+                    let fbconfig = choose_fbconfig(display, screen, &conf.framebuffer)?;
+                    let context = Context::Glx {
+                        glx: GLXARBFunctions::load(),
+                        fbconfig,
+                        ctx: null_mut(),
+                    };
+                    (context, glXGetVisualFromFBConfig(display, fbconfig))
+                }
+                GLContextBackend::Egl => {
+                    let egl = connection.open_egl()?;
+                    let config = egl.choose_config(conf.version, &conf.framebuffer, egl::WINDOW_BIT)?;
+
+                    let mut template: XVisualInfo = MaybeUninit::zeroed().assume_init();
+                    template.visualid = egl.config_attrib(config, egl::NATIVE_VISUAL_ID) as VisualID;
+                    let mut count = 0;
+                    let vi = XGetVisualInfo(display, VisualIDMask, &mut template, &mut count);
+
+                    let context = Context::Egl {
+                        config,
+                        surface: egl::NO_SURFACE,
+                        ctx: egl::NO_CONTEXT,
+                    };
+                    (context, vi)
+                }
+            };
+            if vi.is_null() {
+                return Err(GLDKError::new_unexpected(
+                    "No appropriate visual found.".to_owned(),
+                ));
+            }
+
+            // Every resource created from here on is released by Drop if a later step fails.
+            let mut rwindow = Self {
+                props: Props {
+                    connection,
+                    display,
+                    screen,
+                    window: 0,
+                    visual_id: (*vi).visualid,
+                    colormap: 0,
+                    context,
+                    version: conf.version,
+                    info: None,
+                    format: conf.framebuffer,
+                },
+                running: Cell::new(false),
+            };
+
+            let colormap = XCreateColormap(display, root, (*vi).visual, AllocNone);
+            rwindow.props.colormap = colormap;
+
+            let mut swa: XSetWindowAttributes = MaybeUninit::zeroed().assume_init();
+            swa.colormap = colormap;
+            swa.event_mask = ExposureMask
+                | KeyPressMask
+                | KeyReleaseMask
+                | PointerMotionMask
+                | ButtonPressMask
+                | ButtonReleaseMask
+                | EnterWindowMask
+                | LeaveWindowMask
+                | FocusChangeMask
+                | PropertyChangeMask
+                | StructureNotifyMask;
+
+            let mut window = XCreateWindow(
+                display,
+                root,
+                0,
+                0,
+                width,
+                height,
+                0,
+                (*vi).depth,
+                InputOutput as u32,
+                (*vi).visual,
+                CWColormap | CWEventMask,
+                &mut swa,
+            );
+            XFree(vi as *mut _);
+            if window == 0 {
+                return Err(GLDKError::new_unexpected(
+                    "Can't create window.".to_owned(),
+                ));
+            }
+            rwindow.props.window = window;
+            rwindow.props.connection.register(window, width, height);
+            rwindow.props.connection.select_xinput(window);
+
+            match (&mut rwindow.props.context, share.map(|share| &share.props.context)) {
+                (Context::Glx { glx, fbconfig, ctx }, share) => {
+                    let share_ctx = match share {
+                        None => null_mut(),
+                        Some(Context::Glx { ctx, .. }) => *ctx,
+                        Some(Context::Egl { .. }) => return Err(mixed_backends_error()),
+                    };
+                    rwindow.props.format = query_format(display, *fbconfig);
+
+                    let (created, version) = conf.negotiate(|conf| {
+                        create_context(display, screen, *fbconfig, share_ctx, glx, conf)
+                    })?;
+                    *ctx = created;
+                    rwindow.props.version = version;
+
+                    let (previous_display, previous_drawable, previous_ctx) =
+                        (glXGetCurrentDisplay(), glXGetCurrentDrawable(), glXGetCurrentContext());
+                    glXMakeCurrent(display, window, created);
+                    rwindow.props.info = Some(ContextInfo::query(version, get_glx_proc_address));
+                    if previous_ctx.is_null() {
+                        glXMakeCurrent(display, 0, null_mut());
+                    } else {
+                        glXMakeCurrent(previous_display, previous_drawable, previous_ctx);
+                    }
+                }
+                (Context::Egl { config, surface, ctx }, share) => {
+                    let egl = rwindow.props.connection.egl();
+                    let share_ctx = match share {
+                        None => egl::NO_CONTEXT,
+                        Some(Context::Egl { ctx, .. }) => *ctx,
+                        Some(Context::Glx { .. }) => return Err(mixed_backends_error()),
+                    };
+
+                    *surface = egl.create_window_surface(
+                        *config,
+                        &mut window as *mut Window as *mut c_void,
+                        window as egl::types::EGLNativeWindowType,
+                        conf.framebuffer.srgb,
+                    )?;
+                    // Window surfaces are always back buffered.
+                    rwindow.props.format = egl.query_format(*config, true, conf.framebuffer.srgb);
+
+                    let (created, version, info) =
+                        egl.create_context_with_info(*config, *surface, share_ctx, &conf)?;
+                    *ctx = created;
+                    rwindow.props.version = version;
+                    rwindow.props.info = Some(info);
+                }
+            }
+
+            let mut wm_delete_window = rwindow.props.connection.wm_delete_window;
+            XSetWMProtocols(display, window, &mut wm_delete_window, 1);
+
+            rwindow.set_window_title(title);
+
+            Ok(rwindow)
+        }
+    }
+
+    pub fn connection(&self) -> &Rc<XConnection> {
+        &self.props.connection
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const c_void {
+        match self.props.context {
+            Context::Glx { .. } => get_glx_proc_address(addr),
+            Context::Egl { .. } => egl_impl::get_proc_address(addr),
+        }
+    }
+
+    pub fn get_gl_version(&self) -> GLVersion {
+        self.props.version
+    }
+
+    pub fn get_context_info(&self) -> &ContextInfo {
+        self.props.info.as_ref().unwrap()
+    }
+
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        self.props.format
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.props.connection.scale_factor
+    }
+
+    pub fn handle(&self) -> RawWindowHandle {
+        let mut window_handle = XlibWindowHandle::empty();
+        window_handle.window = self.props.window;
+        window_handle.visual_id = self.props.visual_id;
+        RawWindowHandle::Xlib(window_handle)
+    }
+
+    pub fn display_handle(&self) -> RawDisplayHandle {
+        let mut display_handle = XlibDisplayHandle::empty();
+        display_handle.display = self.props.display as *mut c_void;
+        display_handle.screen = self.props.screen;
+        RawDisplayHandle::Xlib(display_handle)
+    }
+
+    pub fn id(&self) -> WindowID {
+        WindowID(self.props.window)
+    }
+
+    pub fn swap_buffers(&self) {
+        unsafe {
+            match &self.props.context {
+                Context::Glx { .. } => glXSwapBuffers(self.props.display, self.props.window),
+                Context::Egl { surface, .. } => {
+                    egl::SwapBuffers(self.props.connection.egl().raw, *surface);
+                }
+            }
+        }
+    }
+
+    pub fn make_current(&self) {
+        unsafe {
+            match &self.props.context {
+                Context::Glx { ctx, .. } => {
+                    glXMakeCurrent(self.props.display, self.props.window, *ctx);
+                }
+                Context::Egl { surface, ctx, .. } => {
+                    egl::MakeCurrent(self.props.connection.egl().raw, *surface, *surface, *ctx);
+                }
+            }
+        }
+    }
+
+    pub fn swap_interval(&self, enable: bool) {
+        unsafe {
+            match &self.props.context {
+                Context::Glx { glx, .. } => {
+                    if let Some(f) = glx.glXSwapIntervalEXT {
+                        f(self.props.display, self.props.window, enable as c_int);
+                    } else if let Some(f) = glx.glXSwapIntervalMESA {
+                        f(enable as u32);
+                    }
+                }
+                // Applies to the surface bound to the current context.
+                Context::Egl { .. } => {
+                    egl::SwapInterval(self.props.connection.egl().raw, enable as egl::types::EGLint);
+                }
+            }
+        }
+    }
+
+    pub fn run<F>(&self, mut callback: F) -> i32
+    where
+        F: FnMut(WindowEvent, &mut ControlFlow),
+    {
+        let id = self.id();
+        let mut control_flow = ControlFlow::default();
+        self.running.set(true);
+        loop {
+            if let ControlFlow::Exit(code) = control_flow {
+                return code;
+            }
+            if !self.running.get() {
+                return 0;
+            }
+            match self.props.connection.wait_event(control_flow.timeout()) {
+                Ok(Some((event_id, event))) if event_id == id => callback(event, &mut control_flow),
+                Ok(None) if control_flow.is_due() => callback(WindowEvent::RedrawRequested, &mut control_flow),
+                Ok(_) => {}
+                Err(_) => return 1,
+            }
+        }
+    }
+
+    pub fn show(&self) {
+        unsafe {
+            XMapWindow(self.props.display, self.props.window);
+            XFlush(self.props.display);
+        }
+    }
+
+    pub fn hide(&self) {
+        unsafe {
+            XUnmapWindow(self.props.display, self.props.window);
+            XFlush(self.props.display);
+        }
+    }
+
+    pub fn set_window_title(&self, title: &str) {
+        let c_title = CString::new(title).unwrap_or_default();
+        unsafe {
+            XStoreName(self.props.display, self.props.window, c_title.as_ptr());
+            XChangeProperty(
+                self.props.display,
+                self.props.window,
+                intern_atom(self.props.display, "_NET_WM_NAME"),
+                intern_atom(self.props.display, "UTF8_STRING"),
+                8,
+                PropModeReplace,
+                title.as_ptr(),
+                title.len() as c_int,
+            );
+            XFlush(self.props.display);
+        }
+    }
+
+    pub fn set_window_border_width(&self, width: u32) {
+        unsafe {
+            XSetWindowBorderWidth(self.props.display, self.props.window, width);
+            XFlush(self.props.display);
+        }
+    }
+
+    pub fn get_window_size(&self) -> (u32, u32) {
+        unsafe {
+            let mut attributes: XWindowAttributes = MaybeUninit::zeroed().assume_init();
+            XGetWindowAttributes(self.props.display, self.props.window, &mut attributes);
+            (attributes.width as u32, attributes.height as u32)
+        }
+    }
+
+    pub fn get_window_pos(&self) -> (u32, u32) {
+        unsafe {
+            let (mut x, mut y) = (0, 0);
+            let mut child = 0;
+            XTranslateCoordinates(
+                self.props.display,
+                self.props.window,
+                XRootWindow(self.props.display, self.props.screen),
+                0,
+                0,
+                &mut x,
+                &mut y,
+                &mut child,
+            );
+            (x.max(0) as u32, y.max(0) as u32)
+        }
+    }
+
+    pub fn set_undecorated(&self, b: bool) {
+        // flags, functions, decorations, input_mode, status
+        let hints: [c_long; 5] = [2, 0, !b as c_long, 0, 0];
+        unsafe {
+            let motif_wm_hints = intern_atom(self.props.display, "_MOTIF_WM_HINTS");
+            XChangeProperty(
+                self.props.display,
+                self.props.window,
+                motif_wm_hints,
+                motif_wm_hints,
+                32,
+                PropModeReplace,
+                hints.as_ptr() as *const c_uchar,
+                hints.len() as c_int,
+            );
+            XFlush(self.props.display);
+        }
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        let action = if maximized {
+            _NET_WM_STATE_ADD
+        } else {
+            _NET_WM_STATE_REMOVE
+        };
+        unsafe {
+            let display = self.props.display;
+            let mut message: XClientMessageEvent = MaybeUninit::zeroed().assume_init();
+            message.type_ = ClientMessage;
+            message.window = self.props.window;
+            message.message_type = intern_atom(display, "_NET_WM_STATE");
+            message.format = 32;
+            message.data.set_long(0, action);
+            message.data.set_long(1, intern_atom(display, "_NET_WM_STATE_MAXIMIZED_HORZ") as c_long);
+            message.data.set_long(2, intern_atom(display, "_NET_WM_STATE_MAXIMIZED_VERT") as c_long);
+            message.data.set_long(3, 1);
+
+            let mut event = XEvent::from(message);
+            XSendEvent(
+                display,
+                XRootWindow(display, self.props.screen),
+                False,
+                SubstructureRedirectMask | SubstructureNotifyMask,
+                &mut event,
+            );
+            XFlush(display);
+        }
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        unsafe {
+            if minimized {
+                XIconifyWindow(self.props.display, self.props.window, self.props.screen);
+            } else {
+                XMapWindow(self.props.display, self.props.window);
+            }
+            XFlush(self.props.display);
+        }
+    }
+
+    pub fn keyboard_state(&self) -> KeyboardState {
+        self.props.connection.keyboard_state(self.props.window)
+    }
+
+    pub fn set_ime_enabled(&self, enabled: bool) {
+        self.props.connection.set_ime_enabled(self.props.window, enabled);
+    }
+
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.props.connection.set_ime_position(self.props.window, x, y);
+    }
+
+    pub fn quit(&self) {
+        self.running.set(false);
+    }
+}
+
+impl Drop for RWindow {
+    fn drop(&mut self) {
+        unsafe {
+            match &self.props.context {
+                Context::Glx { ctx, .. } => {
+                    if !ctx.is_null() {
+                        if glXGetCurrentContext() == *ctx {
+                            glXMakeCurrent(self.props.display, 0, null_mut());
+                        }
+                        glXDestroyContext(self.props.display, *ctx);
+                    }
+                }
+                Context::Egl { surface, ctx, .. } => {
+                    let egl = self.props.connection.egl();
+                    if egl::GetCurrentContext() == *ctx {
+                        egl::MakeCurrent(egl.raw, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+                    }
+                    if *ctx != egl::NO_CONTEXT {
+                        egl::DestroyContext(egl.raw, *ctx);
+                    }
+                    if *surface != egl::NO_SURFACE {
+                        egl::DestroySurface(egl.raw, *surface);
+                    }
+                }
+            }
+            if self.props.window != 0 {
+                self.props.connection.unregister(self.props.window);
+                XDestroyWindow(self.props.display, self.props.window);
+            }
+            if self.props.colormap != 0 {
+                XFreeColormap(self.props.display, self.props.colormap);
+            }
+            XFlush(self.props.display);
+        }
+    }
+}
+
+fn get_glx_proc_address(addr: &str) -> *const c_void {
+    let addr = CString::new(addr.as_bytes()).unwrap();
+
+    unsafe {
+        match glXGetProcAddressARB(addr.as_ptr() as *const c_uchar) {
+            Some(p) => p as *const c_void,
+            None => std::ptr::null(),
+        }
+    }
+}
+
+fn mixed_backends_error() -> GLDKError {
+    GLDKError::new_simple(ErrorKind::InvalidConfig(
+        "Contexts created through different backends can't share objects.".to_owned(),
+    ))
+}
+
+static X_ERROR_OCCURRED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn x_error_handler(_display: *mut Display, _event: *mut XErrorEvent) -> c_int {
+    X_ERROR_OCCURRED.store(true, Ordering::SeqCst);
+    0
+}
+
+fn choose_fbconfig(
+    display: *mut Display,
+    screen: c_int,
+    format: &GLFramebufferFormat,
+) -> Result<GLXFBConfig, GLDKError> {
+    let mut att = vec![
+        GLX_X_RENDERABLE,
+        True,
+        GLX_DRAWABLE_TYPE,
+        GLX_WINDOW_BIT,
+        GLX_RENDER_TYPE,
+        GLX_RGBA_BIT,
+        GLX_X_VISUAL_TYPE,
+        GLX_TRUE_COLOR,
+        GLX_RED_SIZE,
+        format.red_bits as c_int,
+        GLX_GREEN_SIZE,
+        format.green_bits as c_int,
+        GLX_BLUE_SIZE,
+        format.blue_bits as c_int,
+        GLX_ALPHA_SIZE,
+        format.alpha_bits as c_int,
+        GLX_DEPTH_SIZE,
+        format.depth_bits as c_int,
+        GLX_STENCIL_SIZE,
+        format.stencil_bits as c_int,
+        GLX_DOUBLEBUFFER,
+        format.double_buffer as c_int,
+    ];
+
+    if format.samples > 0 {
+        if !is_extension_supported(display, screen, "GLX_ARB_multisample") {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "GLX_ARB_multisample".to_owned(),
+            )));
+        }
+        att.push(GLX_SAMPLE_BUFFERS);
+        att.push(1);
+        att.push(GLX_SAMPLES);
+        att.push(format.samples as c_int);
+    }
+
+    if format.srgb {
+        if !is_extension_supported(display, screen, "GLX_ARB_framebuffer_sRGB")
+            && !is_extension_supported(display, screen, "GLX_EXT_framebuffer_sRGB")
+        {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "GLX_ARB_framebuffer_sRGB".to_owned(),
+            )));
+        }
+        att.push(GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB);
+        att.push(True);
+    }
+
+    att.push(0);
+
+    unsafe {
+        let mut count = 0;
+        let configs = glXChooseFBConfig(display, screen, att.as_ptr(), &mut count);
+        if configs.is_null() || count == 0 {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(format!(
+                "No framebuffer config matches {:?}",
+                format
+            ))));
+        }
+        let fbconfig = *configs;
+        XFree(configs as *mut _);
+        Ok(fbconfig)
+    }
+}
+
+fn query_format(display: *mut Display, fbconfig: GLXFBConfig) -> GLFramebufferFormat {
+    let get = |attribute: c_int| unsafe {
+        let mut value = 0;
+        glXGetFBConfigAttrib(display, fbconfig, attribute, &mut value);
+        value
+    };
+
+    GLFramebufferFormat {
+        red_bits: get(GLX_RED_SIZE) as u8,
+        green_bits: get(GLX_GREEN_SIZE) as u8,
+        blue_bits: get(GLX_BLUE_SIZE) as u8,
+        alpha_bits: get(GLX_ALPHA_SIZE) as u8,
+        depth_bits: get(GLX_DEPTH_SIZE) as u8,
+        stencil_bits: get(GLX_STENCIL_SIZE) as u8,
+        double_buffer: get(GLX_DOUBLEBUFFER) != 0,
+        samples: get(GLX_SAMPLES) as u8,
+        srgb: get(GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0,
+    }
+}
+
+fn create_context(
+    display: *mut Display,
+    screen: c_int,
+    fbconfig: GLXFBConfig,
+    share: GLXContext,
+    glx: &GLXARBFunctions,
+    conf: &GLConfig,
+) -> Result<GLXContext, GLDKError> {
+    let create_context_attribs = match glx.glXCreateContextAttribsARB {
+        Some(f) if is_extension_supported(display, screen, "GLX_ARB_create_context") => f,
+        _ => {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "GLX_ARB_create_context".to_owned(),
+            )))
+        }
+    };
+
+    let (major, minor) = conf.version.major_minor();
+    let profile = if conf.version.is_es() {
+        // GLX_EXT_create_context_es2_profile only covers ES 2.0; ES 3.x needs the generalised extension.
+        let supported = is_extension_supported(display, screen, "GLX_EXT_create_context_es_profile")
+            || (major == 2
+                && is_extension_supported(display, screen, "GLX_EXT_create_context_es2_profile"));
+        if !supported {
+            return Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)));
+        }
+        GLX_CONTEXT_ES2_PROFILE_BIT_EXT
+    } else {
+        match conf.profile() {
+            GLProfile::Core => arb::GLX_CONTEXT_CORE_PROFILE_BIT_ARB,
+            GLProfile::Compatibility => arb::GLX_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+        }
+    };
+
+    let mut flags = 0;
+    if conf.flags.debug {
+        flags |= arb::GLX_CONTEXT_DEBUG_BIT_ARB;
+    }
+    if conf.flags.forward_compatible {
+        flags |= arb::GLX_CONTEXT_FORWARD_COMPATIBLE_BIT_ARB;
+    }
+
+    let mut att = vec![
+        arb::GLX_CONTEXT_MAJOR_VERSION_ARB,
+        major as c_int,
+        arb::GLX_CONTEXT_MINOR_VERSION_ARB,
+        minor as c_int,
+        arb::GLX_CONTEXT_PROFILE_MASK_ARB,
+        profile,
+    ];
+
+    if let Some(strategy) = conf.flags.robust_access {
+        if !is_extension_supported(display, screen, "GLX_ARB_create_context_robustness") {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "GLX_ARB_create_context_robustness".to_owned(),
+            )));
+        }
+        flags |= GLX_CONTEXT_ROBUST_ACCESS_BIT_ARB;
+        att.push(GLX_CONTEXT_RESET_NOTIFICATION_STRATEGY_ARB);
+        att.push(match strategy {
+            GLResetNotification::NoResetNotification => GLX_NO_RESET_NOTIFICATION_ARB,
+            GLResetNotification::LoseContextOnReset => GLX_LOSE_CONTEXT_ON_RESET_ARB,
+        });
+    }
+
+    if conf.flags.no_error {
+        if !is_extension_supported(display, screen, "GLX_ARB_create_context_no_error") {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "GLX_ARB_create_context_no_error".to_owned(),
+            )));
+        }
+        att.push(GLX_CONTEXT_OPENGL_NO_ERROR_ARB);
+        att.push(True);
+    }
+
+    att.push(arb::GLX_CONTEXT_FLAGS_ARB);
+    att.push(flags);
+    att.push(0);
+
+    unsafe {
+        // A failing glXCreateContextAttribsARB raises an X error instead of just returning null,
+        // which would terminate the process with the default handler.
+        X_ERROR_OCCURRED.store(false, Ordering::SeqCst);
+        let old_handler = XSetErrorHandler(Some(x_error_handler));
+        let ctx = create_context_attribs(display, fbconfig, share, True, att.as_ptr());
+        XSync(display, False);
+        XSetErrorHandler(old_handler);
+
+        if ctx.is_null() || X_ERROR_OCCURRED.load(Ordering::SeqCst) {
+            if !ctx.is_null() {
+                glXDestroyContext(display, ctx);
+            }
+            return Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(
+                conf.version,
+            )));
+        }
+
+        Ok(ctx)
+    }
+}
+
+fn is_extension_supported(display: *mut Display, screen: c_int, name: &str) -> bool {
+    unsafe {
+        let extensions = glXQueryExtensionsString(display, screen);
+        if extensions.is_null() {
+            return false;
+        }
+        CStr::from_ptr(extensions)
+            .to_string_lossy()
+            .split_whitespace()
+            .any(|e| e == name)
+    }
+}
+
+fn keyboard_input(key: &mut XKeyEvent) -> KeyboardInput {
+    let event: *mut XKeyEvent = key;
+    let keysym = key_keysym(|level| unsafe { XLookupKeysym(event, level) as c_uint });
+
+    // These are the usual assignments, Alt and the Windows key can be bound elsewhere with xmodmap.
+    let modifiers = Modifiers {
+        shift: key.state & ShiftMask != 0,
+        ctrl: key.state & ControlMask != 0,
+        alt: key.state & Mod1Mask != 0,
+        super_key: key.state & Mod4Mask != 0,
+        caps_lock: key.state & LockMask != 0,
+        num_lock: key.state & Mod2Mask != 0,
+    };
+
+    KeyboardInput {
+        keycode: virtual_keycode(keysym),
+        scancode: key.keycode,
+        modifiers,
+        // Only the window's keyboard state knows.
+        repeat: false,
+    }
+}
+
+/// The keysym a key is named after, given the keysyms of its levels. Also used on Wayland, whose
+/// keysyms are the same.
+#[allow(non_upper_case_globals)]
+pub(super) fn key_keysym(level: impl Fn(c_int) -> c_uint) -> c_uint {
+    // Keypad digits are on the second level, the first one being Home, End and so on.
+    let keypad = level(1);
+    match keypad {
+        XK_KP_0..=XK_KP_9 | XK_KP_Decimal | XK_KP_Separator => keypad,
+        _ => level(0),
+    }
+}
+
+#[allow(non_upper_case_globals)]
+pub(super) fn virtual_keycode(keysym: c_uint) -> VirtualKeyCode {
+    match keysym {
+        XK_0..=XK_9 => VirtualKeyCode::digit(keysym - XK_0),
+        XK_a..=XK_z => VirtualKeyCode::letter(keysym - XK_a),
+        XK_F1..=XK_F24 => VirtualKeyCode::function_key(keysym - XK_F1),
+        XK_KP_0..=XK_KP_9 => VirtualKeyCode::numpad_digit(keysym - XK_KP_0),
+
+        XK_Escape => VirtualKeyCode::Escape,
+        XK_Print => VirtualKeyCode::PrintScreen,
+        XK_Scroll_Lock => VirtualKeyCode::ScrollLock,
+        XK_Pause => VirtualKeyCode::Pause,
+
+        XK_Insert => VirtualKeyCode::Insert,
+        XK_Delete => VirtualKeyCode::Delete,
+        XK_Home => VirtualKeyCode::Home,
+        XK_End => VirtualKeyCode::End,
+        XK_Page_Up => VirtualKeyCode::PageUp,
+        XK_Page_Down => VirtualKeyCode::PageDown,
+
+        XK_Left => VirtualKeyCode::Left,
+        XK_Up => VirtualKeyCode::Up,
+        XK_Right => VirtualKeyCode::Right,
+        XK_Down => VirtualKeyCode::Down,
+
+        XK_BackSpace => VirtualKeyCode::Backspace,
+        XK_Return => VirtualKeyCode::Return,
+        XK_space => VirtualKeyCode::Space,
+        // Shift+Tab is its own keysym on most layouts.
+        XK_Tab | XK_ISO_Left_Tab => VirtualKeyCode::Tab,
+        XK_Caps_Lock => VirtualKeyCode::CapsLock,
+        XK_Menu => VirtualKeyCode::Menu,
+
+        XK_KP_Add => VirtualKeyCode::NumpadAdd,
+        XK_KP_Subtract => VirtualKeyCode::NumpadSubtract,
+        XK_KP_Multiply => VirtualKeyCode::NumpadMultiply,
+        XK_KP_Divide => VirtualKeyCode::NumpadDivide,
+        XK_KP_Decimal | XK_KP_Separator => VirtualKeyCode::NumpadDecimal,
+        XK_KP_Enter => VirtualKeyCode::NumpadEnter,
+        XK_Num_Lock => VirtualKeyCode::NumLock,
+
+        XK_Shift_L => VirtualKeyCode::LShift,
+        XK_Shift_R => VirtualKeyCode::RShift,
+        XK_Control_L => VirtualKeyCode::LControl,
+        XK_Control_R => VirtualKeyCode::RControl,
+        XK_Alt_L => VirtualKeyCode::LAlt,
+        // AltGr on international layouts.
+        XK_Alt_R | XK_ISO_Level3_Shift => VirtualKeyCode::RAlt,
+        XK_Super_L => VirtualKeyCode::LSuper,
+        XK_Super_R => VirtualKeyCode::RSuper,
+
+        XK_grave => VirtualKeyCode::Grave,
+        XK_minus => VirtualKeyCode::Minus,
+        XK_equal => VirtualKeyCode::Equals,
+        XK_bracketleft => VirtualKeyCode::LBracket,
+        XK_bracketright => VirtualKeyCode::RBracket,
+        XK_backslash => VirtualKeyCode::Backslash,
+        XK_semicolon => VirtualKeyCode::Semicolon,
+        XK_apostrophe => VirtualKeyCode::Apostrophe,
+        XK_comma => VirtualKeyCode::Comma,
+        XK_period => VirtualKeyCode::Period,
+        XK_slash => VirtualKeyCode::Slash,
+
+        _ => VirtualKeyCode::Unknown,
+    }
+}
+
+/// The major opcode of XInput if the server has version 2.1.
+unsafe fn query_xinput(display: *mut Display) -> Option<c_int> {
+    let (mut opcode, mut event, mut error) = (0, 0, 0);
+    if XQueryExtension(display, c"XInputExtension".as_ptr(), &mut opcode, &mut event, &mut error) == False {
+        return None;
+    }
+    let (mut major, mut minor) = (2, 1);
+    if XIQueryVersion(display, &mut major, &mut minor) != Success as c_int || (major, minor) < (2, 1) {
+        return None;
+    }
+    Some(opcode)
+}
+
+/// Opens the user's input method, which turns dead keys and compose sequences into text.
+unsafe fn open_input_method(display: *mut Display) -> XIM {
+    // Input methods only produce UTF-8 under a UTF-8 locale. "C" means the program never picked one.
+    let locale = setlocale(LC_CTYPE, null());
+    if !locale.is_null() && CStr::from_ptr(locale).to_bytes() == b"C" {
+        setlocale(LC_CTYPE, c"".as_ptr());
+    }
+    XSetLocaleModifiers(c"".as_ptr());
+    XOpenIM(display, null_mut(), null_mut(), null_mut())
+}
+
+/// The input styles `im` supports, none when it couldn't be opened.
+unsafe fn input_styles(im: XIM) -> Vec<XIMStyle> {
+    if im.is_null() {
+        return Vec::new();
+    }
+    let mut styles: *mut XIMStyles = null_mut();
+    let failed = XGetIMValues(im, XNQueryInputStyle_0.as_ptr(), &mut styles, null_mut::<c_void>());
+    if !failed.is_null() || styles.is_null() {
+        return Vec::new();
+    }
+    let supported = std::slice::from_raw_parts((*styles).supported_styles, (*styles).count_styles as usize).to_vec();
+    XFree(styles as *mut _);
+    supported
+}
+
+/// Characters of an `XIMText`, `None` when only its highlighting changed.
+unsafe fn xim_text(text: *const XIMText) -> Option<Vec<char>> {
+    if text.is_null() {
+        return Some(Vec::new());
+    }
+    let text = &*text;
+    // Despite its binding, `wide_char` is a pointer, to UCS-4 on every platform Xlib runs on here.
+    let wide_char = *(addr_of!(text.string) as *const *const u32);
+    if text.encoding_is_wchar != 0 {
+        if wide_char.is_null() {
+            return None;
+        }
+        let units = std::slice::from_raw_parts(wide_char, text.length as usize);
+        return Some(units.iter().filter_map(|&unit| char::from_u32(unit)).collect());
+    }
+    if text.string.multi_byte.is_null() {
+        return None;
+    }
+    // In the locale's encoding, which is UTF-8 wherever `Xutf8LookupString` works too.
+    Some(CStr::from_ptr(text.string.multi_byte).to_string_lossy().chars().collect())
+}
+
+unsafe extern "C" fn preedit_start(_ic: XIC, client_data: XPointer, _call_data: XPointer) -> c_int {
+    let preedit = &*(client_data as *const Preedit);
+    preedit.clear();
+    // No limit on the length of the preedit.
+    -1
+}
+
+unsafe extern "C" fn preedit_done(_ic: XIC, client_data: XPointer, _call_data: XPointer) -> c_int {
+    let preedit = &*(client_data as *const Preedit);
+    preedit.clear();
+    preedit.emit();
+    0
+}
+
+unsafe extern "C" fn preedit_draw(_ic: XIC, client_data: XPointer, call_data: XPointer) -> c_int {
+    let preedit = &*(client_data as *const Preedit);
+    let draw = &*(call_data as *const XIMPreeditDrawCallbackStruct);
+    if let Some(chars) = xim_text(draw.text) {
+        let mut text = preedit.text.borrow_mut();
+        let first = (draw.chg_first.max(0) as usize).min(text.len());
+        let last = (first + draw.chg_length.max(0) as usize).min(text.len());
+        text.splice(first..last, chars);
+    }
+    preedit.caret.set(Some(draw.caret.max(0) as usize));
+    preedit.emit();
+    0
+}
+
+unsafe extern "C" fn preedit_caret(_ic: XIC, client_data: XPointer, call_data: XPointer) -> c_int {
+    let preedit = &*(client_data as *const Preedit);
+    let call = &mut *(call_data as *mut XIMPreeditCaretCallbackStruct);
+    let len = preedit.text.borrow().len();
+    let current = preedit.caret.get().unwrap_or(len);
+    // The client moves the caret and reports back where it ended up.
+    let position = match call.direction {
+        XIMCaretDirection::XIMAbsolutePosition => call.position.max(0) as usize,
+        XIMCaretDirection::XIMForwardChar => current + 1,
+        XIMCaretDirection::XIMBackwardChar => current.saturating_sub(1),
+        XIMCaretDirection::XIMLineStart => 0,
+        XIMCaretDirection::XIMLineEnd => len,
+        _ => current,
+    }
+    .min(len);
+    call.position = position as c_int;
+    let visible = call.style != XIMCaretStyle::XIMIsInvisible;
+    preedit.caret.set(visible.then_some(position));
+    preedit.emit();
+    0
+}
+
+/// Reads `Xft.dpi` from the resource database, which is where desktops publish their scaling.
+fn xft_dpi(display: *mut Display) -> Option<f64> {
+    let resources = unsafe { XResourceManagerString(display) };
+    if resources.is_null() {
+        return None;
+    }
+    let resources = unsafe { CStr::from_ptr(resources) }.to_string_lossy();
+    resources
+        .lines()
+        .find_map(|line| line.strip_prefix("Xft.dpi:"))
+        .and_then(|dpi| dpi.trim().parse::<f64>().ok())
+        .filter(|dpi| *dpi > 0.0)
+}
+
+fn intern_atom(display: *mut Display, name: &str) -> Atom {
+    let name = CString::new(name).unwrap();
+    unsafe { XInternAtom(display, name.as_ptr(), False) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preedit() -> Preedit {
+        Preedit {
+            window: 1,
+            events: Rc::new(RefCell::new(VecDeque::new())),
+            text: RefCell::new(Vec::new()),
+            caret: Cell::new(None),
+        }
+    }
+
+    fn last_event(preedit: &Preedit) -> WindowEvent {
+        let (id, event) = preedit.events.borrow_mut().pop_back().unwrap();
+        assert_eq!(id, WindowID(1));
+        event
+    }
+
+    fn ime_preedit(text: &str, cursor: Option<usize>) -> WindowEvent {
+        WindowEvent::ImePreedit {
+            text: text.to_owned(),
+            cursor,
+        }
+    }
+
+    /// Draws `text` in the locale's multi-byte encoding, or deletes the range when it is `None`.
+    fn draw(preedit: &Preedit, caret: c_int, chg_first: c_int, chg_length: c_int, text: Option<&str>) -> WindowEvent {
+        let string = text.map(|text| CString::new(text).unwrap());
+        let mut xim_text = string.as_ref().map(|string| XIMText {
+            length: string.to_str().unwrap().chars().count() as c_ushort,
+            feedback: null_mut(),
+            encoding_is_wchar: False,
+            string: XIMTextString {
+                multi_byte: string.as_ptr() as *mut c_char,
+            },
+        });
+        let mut call = XIMPreeditDrawCallbackStruct {
+            caret,
+            chg_first,
+            chg_length,
+            text: xim_text.as_mut().map_or(null_mut(), |text| text as *mut XIMText),
+        };
+        unsafe {
+            preedit_draw(
+                null_mut(),
+                preedit as *const Preedit as XPointer,
+                &mut call as *mut _ as XPointer,
+            );
+        }
+        last_event(preedit)
+    }
+
+    fn move_caret(preedit: &Preedit, direction: XIMCaretDirection, position: c_int, style: XIMCaretStyle) -> c_int {
+        let mut call = XIMPreeditCaretCallbackStruct {
+            position,
+            direction,
+            style,
+        };
+        unsafe {
+            preedit_caret(
+                null_mut(),
+                preedit as *const Preedit as XPointer,
+                &mut call as *mut _ as XPointer,
+            );
+        }
+        call.position
+    }
+
+    #[test]
+    fn preedit_draw_replaces_the_changed_range() {
+        let preedit = preedit();
+        assert_eq!(draw(&preedit, 2, 0, 0, Some("ka")), ime_preedit("ka", Some(2)));
+        // The cursor is a byte offset, the caret a character index.
+        assert_eq!(draw(&preedit, 1, 0, 2, Some("か")), ime_preedit("か", Some(3)));
+        assert_eq!(draw(&preedit, 2, 1, 0, Some("ん")), ime_preedit("かん", Some(6)));
+        assert_eq!(draw(&preedit, 0, 0, 1, None), ime_preedit("ん", Some(0)));
+        // Out of range changes and carets are clamped.
+        assert_eq!(draw(&preedit, 9, 5, 3, Some("!")), ime_preedit("ん!", Some(4)));
+    }
+
+    #[test]
+    fn preedit_draw_reads_wide_chars() {
+        let preedit = preedit();
+        let units: Vec<u32> = "日本".chars().map(|c| c as u32).collect();
+        let mut text = XIMText {
+            length: units.len() as c_ushort,
+            feedback: null_mut(),
+            encoding_is_wchar: True,
+            string: XIMTextString {
+                multi_byte: units.as_ptr() as *mut c_char,
+            },
+        };
+        let mut call = XIMPreeditDrawCallbackStruct {
+            caret: 1,
+            chg_first: 0,
+            chg_length: 0,
+            text: &mut text,
+        };
+        unsafe {
+            preedit_draw(
+                null_mut(),
+                &preedit as *const Preedit as XPointer,
+                &mut call as *mut _ as XPointer,
+            );
+        }
+        assert_eq!(last_event(&preedit), ime_preedit("日本", Some(3)));
+    }
+
+    #[test]
+    fn preedit_caret_moves_within_the_text() {
+        use XIMCaretDirection::*;
+        use XIMCaretStyle::*;
+
+        let preedit = preedit();
+        draw(&preedit, 3, 0, 0, Some("abc"));
+        assert_eq!(move_caret(&preedit, XIMBackwardChar, 0, XIMIsPrimary), 2);
+        assert_eq!(last_event(&preedit), ime_preedit("abc", Some(2)));
+        assert_eq!(move_caret(&preedit, XIMLineStart, 0, XIMIsPrimary), 0);
+        assert_eq!(move_caret(&preedit, XIMBackwardChar, 0, XIMIsPrimary), 0);
+        assert_eq!(move_caret(&preedit, XIMForwardChar, 0, XIMIsPrimary), 1);
+        assert_eq!(move_caret(&preedit, XIMAbsolutePosition, 10, XIMIsPrimary), 3);
+        assert_eq!(move_caret(&preedit, XIMLineStart, 0, XIMIsInvisible), 0);
+        assert_eq!(last_event(&preedit), ime_preedit("abc", None));
+        // A hidden caret moves from the end of the text.
+        assert_eq!(move_caret(&preedit, XIMBackwardChar, 0, XIMIsPrimary), 2);
+    }
+
+    #[test]
+    fn preedit_done_clears_the_text() {
+        let preedit = preedit();
+        draw(&preedit, 1, 0, 0, Some("a"));
+        unsafe {
+            preedit_done(null_mut(), &preedit as *const Preedit as XPointer, null_mut());
+        }
+        assert_eq!(last_event(&preedit), ime_preedit("", None));
+        assert!(preedit.text.borrow().is_empty());
+    }
+}
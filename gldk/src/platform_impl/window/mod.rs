@@ -1,4 +1,4 @@
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle};
 
 #[cfg(target_os = "linux")]
 pub mod linux;
@@ -21,3 +21,9 @@ unsafe impl HasRawWindowHandle for RWindow {
         self.handle()
     }
 }
+
+unsafe impl HasRawDisplayHandle for RWindow {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.display_handle()
+    }
+}
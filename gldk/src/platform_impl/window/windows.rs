@@ -1,13 +1,20 @@
+use crate::sys::imm::*;
 use crate::sys::{glGetError, wgl, wgl_extra, WGLARBFunctions};
-use crate::window::{KeyCode, WindowEvent, WindowID};
+use crate::window::{
+    ControlFlow, KeyboardInput, KeyboardState, Modifiers, MouseButton, Position, ScrollDelta, VirtualKeyCode, WindowEvent, WindowID,
+};
 use core::ffi::c_void;
 use std::ffi::CString;
 
-use raw_window_handle::{RawWindowHandle, Win32WindowHandle};
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle, Win32WindowHandle, WindowsDisplayHandle};
 
-use crate::{GLConfig, GLVersion};
+use crate::{GLConfig, GLContextBackend, GLFramebufferFormat, GLProfile, GLResetNotification, GLVersion};
 use gwl::window::{Window, WindowBuildAction, WindowBuilder, WindowInstance};
-use std::ptr::{addr_of, addr_of_mut, null_mut};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::os::raw::c_int;
+use std::ptr::{addr_of, addr_of_mut, null, null_mut};
+use std::time::Duration;
 
 use winapi::um::winuser::*;
 
@@ -18,21 +25,27 @@ use winapi::shared::windef::*;
 
 use winapi::um::libloaderapi::{GetModuleHandleA, GetProcAddress};
 use winapi::um::wingdi::{
-    ChoosePixelFormat, SetPixelFormat, SwapBuffers, PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW,
-    PFD_MAIN_PLANE, PFD_SUPPORT_OPENGL, PFD_TYPE_RGBA, PIXELFORMATDESCRIPTOR,
+    ChoosePixelFormat, DescribePixelFormat, GetDeviceCaps, SetPixelFormat, SwapBuffers, LOGPIXELSX,
+    PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW, PFD_MAIN_PLANE, PFD_SUPPORT_OPENGL, PFD_TYPE_RGBA,
+    PIXELFORMATDESCRIPTOR,
 };
-use winapi::um::winnt::PCSTR;
-use crate::error::GLDKError;
+use winapi::um::winnt::{LPCSTR, PCSTR};
+use crate::context::ContextInfo;
+use crate::error::{ErrorKind, GLDKError};
 
 pub struct Props {
     hwnd: Option<HWND>,
     hinstance: Option<HINSTANCE>,
     wgl: Option<WGLARBFunctions>,
     ctx: Option<HGLRC>,
+    version: GLVersion,
+    info: Option<ContextInfo>,
+    format: GLFramebufferFormat,
 }
 
 pub struct BuildAction {
     conf: GLConfig,
+    share: Option<HGLRC>,
     props: *mut Props,
     error: Option<GLDKError>
 }
@@ -44,108 +57,780 @@ impl WindowBuildAction for BuildAction {
         unsafe {
             (*self.props).hwnd = Some(handle.hwnd);
             (*self.props).hinstance = Some(handle.hinstance);
+
+            if let Err(e) = self.create_context(handle) {
+                self.error = Some(e);
+            }
         }
+    }
+}
 
-        let (major, minor) = match self.conf.version {
-            GLVersion::V3_0 => (3, 0),
-            GLVersion::V3_1 => (3, 1),
-            GLVersion::V3_2 => (3, 2),
-            GLVersion::V3_3 => (3, 3),
-            GLVersion::V4_0 => (4, 0),
-            GLVersion::V4_1 => (4, 1),
-            GLVersion::V4_2 => (4, 2),
-            GLVersion::V4_3 => (4, 3),
-            GLVersion::V4_4 => (4, 4),
-            GLVersion::V4_5 => (4, 5),
-            GLVersion::V4_6 => (4, 6),
-        };
+impl BuildAction {
+    unsafe fn create_context(&mut self, handle: &WindowInstance) -> Result<(), GLDKError> {
+        let func = load_wgl_functions(handle.hinstance)?;
 
-        unsafe {
-            let pfd = PIXELFORMATDESCRIPTOR {
-                nSize: std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u16,
-                nVersion: 1,
-                dwFlags: PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | PFD_DOUBLEBUFFER,
-                iPixelType: PFD_TYPE_RGBA,
-                cColorBits: 32,
-                cRedBits: 0,
-                cRedShift: 0,
-                cGreenBits: 0,
-                cGreenShift: 0,
-                cBlueBits: 0,
-                cBlueShift: 0,
-                cAlphaBits: 0,
-                cAlphaShift: 0,
-                cAccumBits: 0,
-                cAccumRedBits: 0,
-                cAccumGreenBits: 0,
-                cAccumBlueBits: 0,
-                cAccumAlphaBits: 0,
-                cDepthBits: 24,
-                cStencilBits: 8,
-                cAuxBuffers: 0,
-                iLayerType: PFD_MAIN_PLANE,
-                bReserved: 0,
-                dwLayerMask: 0,
-                dwVisibleMask: 0,
-                dwDamageMask: 0,
-            };
+        let hdc = GetDC(handle.hwnd);
+        let pixel_format = choose_pixel_format(
+            &func,
+            hdc,
+            &self.conf.framebuffer,
+            wgl_extra::DRAW_TO_WINDOW_ARB,
+        )?;
 
-            let hdc = GetDC(handle.hwnd);
-            let pixel_format = ChoosePixelFormat(hdc, addr_of!(pfd));
+        let mut pfd: PIXELFORMATDESCRIPTOR = std::mem::zeroed();
+        DescribePixelFormat(
+            hdc,
+            pixel_format,
+            std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u32,
+            addr_of_mut!(pfd),
+        );
 
-            if pixel_format == 0 {
-                self.error = Some(GLDKError::new_unexpected("Pixel format is null.".to_owned()));
-                return;
+        if SetPixelFormat(hdc, pixel_format, addr_of!(pfd)) == 0 {
+            return Err(GLDKError::new_unexpected("Can't set pixel format.".to_owned()));
+        }
+
+        (*self.props).format = describe_pixel_format(&func, hdc, pixel_format);
+
+        let share = self.share.unwrap_or(std::ptr::null());
+        let (ctx, version) =
+            self.conf.negotiate(|conf| create_wgl_context(&func, hdc, share, conf))?;
+
+        let (previous_dc, previous_ctx) = (wgl::GetCurrentDC(), wgl::GetCurrentContext());
+        wgl::MakeCurrent(hdc as wgl::types::HDC, ctx as wgl::types::HGLRC);
+        let info = ContextInfo::query(version, get_proc_address);
+        wgl::MakeCurrent(previous_dc, previous_ctx);
+
+        (*self.props).ctx = Some(ctx);
+        (*self.props).version = version;
+        (*self.props).info = Some(info);
+        (*self.props).wgl = Some(func);
+
+        Ok(())
+    }
+}
+
+pub(crate) unsafe fn create_wgl_context(
+    func: &WGLARBFunctions,
+    hdc: HDC,
+    share: HGLRC,
+    conf: &GLConfig,
+) -> Result<HGLRC, GLDKError> {
+    let create_context_attribs = match func.wglCreateContextAttribsARB {
+        Some(f) => f,
+        None => {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "WGL_ARB_create_context".to_owned(),
+            )))
+        }
+    };
+
+    let (major, minor) = conf.version.major_minor();
+    let profile = if conf.version.is_es() {
+        // WGL_EXT_create_context_es2_profile only covers ES 2.0; ES 3.x needs the generalised extension.
+        let supported = func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_EXT_create_context_es_profile")
+            || (major == 2
+                && func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_EXT_create_context_es2_profile"));
+        if !supported {
+            return Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)));
+        }
+        wgl_extra::CONTEXT_ES2_PROFILE_BIT_EXT
+    } else {
+        match conf.profile() {
+            GLProfile::Core => wgl_extra::CONTEXT_CORE_PROFILE_BIT_ARB,
+            GLProfile::Compatibility => wgl_extra::CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+        }
+    };
+
+    let mut flags = 0;
+    if conf.flags.debug {
+        flags |= wgl_extra::CONTEXT_DEBUG_BIT_ARB;
+    }
+    if conf.flags.forward_compatible {
+        flags |= wgl_extra::CONTEXT_FORWARD_COMPATIBLE_BIT_ARB;
+    }
+
+    let mut att = vec![
+        wgl_extra::CONTEXT_MAJOR_VERSION_ARB,
+        major,
+        wgl_extra::CONTEXT_MINOR_VERSION_ARB,
+        minor,
+        wgl_extra::CONTEXT_PROFILE_MASK_ARB,
+        profile,
+    ];
+
+    if let Some(strategy) = conf.flags.robust_access {
+        if !func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_ARB_create_context_robustness") {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "WGL_ARB_create_context_robustness".to_owned(),
+            )));
+        }
+        flags |= wgl_extra::CONTEXT_ROBUST_ACCESS_BIT_ARB;
+        att.push(wgl_extra::CONTEXT_RESET_NOTIFICATION_STRATEGY_ARB);
+        att.push(match strategy {
+            GLResetNotification::NoResetNotification => wgl_extra::NO_RESET_NOTIFICATION_ARB,
+            GLResetNotification::LoseContextOnReset => wgl_extra::LOSE_CONTEXT_ON_RESET_ARB,
+        });
+    }
+
+    if conf.flags.no_error {
+        if !func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_ARB_create_context_no_error") {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "WGL_ARB_create_context_no_error".to_owned(),
+            )));
+        }
+        att.push(wgl_extra::CONTEXT_OPENGL_NO_ERROR_ARB);
+        att.push(1);
+    }
+
+    att.push(wgl_extra::CONTEXT_FLAGS_ARB);
+    att.push(flags);
+    att.push(0);
+
+    let ctx = create_context_attribs(
+        hdc as wgl_extra::types::HDC,
+        share,
+        att.as_ptr(),
+    );
+    if ctx.is_null() {
+        return Err(GLDKError::new_simple(ErrorKind::UnsupportedVersion(conf.version)));
+    }
+
+    Ok(ctx)
+}
+
+pub(crate) fn get_proc_address(addr: &str) -> *const c_void {
+    let addr = CString::new(addr.as_bytes()).unwrap();
+    let addr = addr.as_ptr();
+
+    unsafe {
+        let p = wgl::GetProcAddress(addr) as *const core::ffi::c_void;
+        if !p.is_null() {
+            return p;
+        }
+        // OpenGL 1.1 functions are only exported by opengl32.dll itself.
+        let gl = GetModuleHandleA("Opengl32.dll\0".as_ptr() as *const i8);
+        GetProcAddress(gl, addr as PCSTR) as *const _
+    }
+}
+
+/// wglGetProcAddress only works with a current context, and the pixel format of a window can only be set once,
+/// so the extension functions are loaded through a throwaway window with a legacy context.
+pub(crate) unsafe fn load_wgl_functions(hinstance: HINSTANCE) -> Result<WGLARBFunctions, GLDKError> {
+    let hwnd = CreateWindowExA(
+        0,
+        "STATIC\0".as_ptr() as LPCSTR,
+        "\0".as_ptr() as LPCSTR,
+        WS_POPUP,
+        0,
+        0,
+        1,
+        1,
+        null_mut(),
+        null_mut(),
+        hinstance,
+        null_mut(),
+    );
+    if hwnd.is_null() {
+        return Err(GLDKError::new_unexpected("Can't create dummy window.".to_owned()));
+    }
+
+    let hdc = GetDC(hwnd);
+    let pfd = pixel_format_descriptor(&GLFramebufferFormat::default());
+    let pixel_format = ChoosePixelFormat(hdc, addr_of!(pfd));
+    if pixel_format == 0 || SetPixelFormat(hdc, pixel_format, addr_of!(pfd)) == 0 {
+        ReleaseDC(hwnd, hdc);
+        DestroyWindow(hwnd);
+        return Err(GLDKError::new_unexpected("Can't set pixel format.".to_owned()));
+    }
+
+    let old_ctx = wgl::CreateContext(hdc as wgl::types::HDC);
+    wgl::MakeCurrent(hdc as wgl::types::HDC, old_ctx);
+
+    let func = WGLARBFunctions::load();
+
+    wgl::MakeCurrent(std::ptr::null(), std::ptr::null());
+    wgl::DeleteContext(old_ctx);
+    ReleaseDC(hwnd, hdc);
+    DestroyWindow(hwnd);
+
+    Ok(func)
+}
+
+/// `drawable` is `DRAW_TO_WINDOW_ARB` or `DRAW_TO_PBUFFER_ARB`.
+pub(crate) unsafe fn choose_pixel_format(
+    func: &WGLARBFunctions,
+    hdc: HDC,
+    format: &GLFramebufferFormat,
+    drawable: u32,
+) -> Result<i32, GLDKError> {
+    let choose_pixel_format_arb = match func.wglChoosePixelFormatARB {
+        Some(f) if func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_ARB_pixel_format") => f,
+        _ => {
+            if format.samples > 0 || format.srgb || drawable != wgl_extra::DRAW_TO_WINDOW_ARB {
+                return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                    "WGL_ARB_pixel_format".to_owned(),
+                )));
             }
 
-            if SetPixelFormat(hdc, pixel_format, addr_of!(pfd)) == 0 {
-                self.error = Some(GLDKError::new_unexpected("Can't set pixel format.".to_owned()));
-                return;
+            let pfd = pixel_format_descriptor(format);
+            return match ChoosePixelFormat(hdc, addr_of!(pfd)) {
+                0 => Err(GLDKError::new_unexpected("Pixel format is null.".to_owned())),
+                pixel_format => Ok(pixel_format),
+            };
+        }
+    };
+
+    let mut att = vec![
+        drawable as i32,
+        1,
+        wgl_extra::SUPPORT_OPENGL_ARB as i32,
+        1,
+        wgl_extra::ACCELERATION_ARB as i32,
+        wgl_extra::FULL_ACCELERATION_ARB as i32,
+        wgl_extra::PIXEL_TYPE_ARB as i32,
+        wgl_extra::TYPE_RGBA_ARB as i32,
+        wgl_extra::DOUBLE_BUFFER_ARB as i32,
+        format.double_buffer as i32,
+        wgl_extra::RED_BITS_ARB as i32,
+        format.red_bits as i32,
+        wgl_extra::GREEN_BITS_ARB as i32,
+        format.green_bits as i32,
+        wgl_extra::BLUE_BITS_ARB as i32,
+        format.blue_bits as i32,
+        wgl_extra::ALPHA_BITS_ARB as i32,
+        format.alpha_bits as i32,
+        wgl_extra::DEPTH_BITS_ARB as i32,
+        format.depth_bits as i32,
+        wgl_extra::STENCIL_BITS_ARB as i32,
+        format.stencil_bits as i32,
+    ];
+
+    if format.samples > 0 {
+        if !func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_ARB_multisample") {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "WGL_ARB_multisample".to_owned(),
+            )));
+        }
+        att.push(wgl_extra::SAMPLE_BUFFERS_ARB as i32);
+        att.push(1);
+        att.push(wgl_extra::SAMPLES_ARB as i32);
+        att.push(format.samples as i32);
+    }
+
+    if format.srgb {
+        if !func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_ARB_framebuffer_sRGB")
+            && !func.is_extension_supported(hdc as wgl_extra::types::HDC, "WGL_EXT_framebuffer_sRGB")
+        {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported(
+                "WGL_ARB_framebuffer_sRGB".to_owned(),
+            )));
+        }
+        att.push(wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as i32);
+        att.push(1);
+    }
+
+    att.push(0);
+
+    let mut pixel_format = 0;
+    let mut count = 0;
+    let result = choose_pixel_format_arb(
+        hdc as wgl_extra::types::HDC,
+        att.as_ptr(),
+        std::ptr::null(),
+        1,
+        &mut pixel_format,
+        &mut count,
+    );
+    if result == 0 || count == 0 {
+        return Err(GLDKError::new_simple(ErrorKind::Unsupported(format!(
+            "No pixel format matches {:?}",
+            format
+        ))));
+    }
+
+    Ok(pixel_format)
+}
+
+pub(crate) unsafe fn describe_pixel_format(
+    func: &WGLARBFunctions,
+    hdc: HDC,
+    pixel_format: i32,
+) -> GLFramebufferFormat {
+    let mut pfd: PIXELFORMATDESCRIPTOR = std::mem::zeroed();
+    DescribePixelFormat(
+        hdc,
+        pixel_format,
+        std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u32,
+        addr_of_mut!(pfd),
+    );
+
+    GLFramebufferFormat {
+        red_bits: pfd.cRedBits,
+        green_bits: pfd.cGreenBits,
+        blue_bits: pfd.cBlueBits,
+        alpha_bits: pfd.cAlphaBits,
+        depth_bits: pfd.cDepthBits,
+        stencil_bits: pfd.cStencilBits,
+        double_buffer: pfd.dwFlags & PFD_DOUBLEBUFFER != 0,
+        samples: get_pixel_format_attrib(func, hdc, pixel_format, wgl_extra::SAMPLES_ARB)
+            .unwrap_or(0) as u8,
+        srgb: get_pixel_format_attrib(func, hdc, pixel_format, wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB)
+            .unwrap_or(0) != 0,
+    }
+}
+
+unsafe fn get_pixel_format_attrib(
+    func: &WGLARBFunctions,
+    hdc: HDC,
+    pixel_format: i32,
+    attribute: u32,
+) -> Option<i32> {
+    let f = func.wglGetPixelFormatAttribivARB?;
+    let attribute = attribute as i32;
+    let mut value = 0;
+    match f(hdc as wgl_extra::types::HDC, pixel_format, 0, 1, &attribute, &mut value) {
+        0 => None,
+        _ => Some(value),
+    }
+}
+
+fn pixel_format_descriptor(format: &GLFramebufferFormat) -> PIXELFORMATDESCRIPTOR {
+    let mut flags = PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL;
+    if format.double_buffer {
+        flags |= PFD_DOUBLEBUFFER;
+    }
+
+    PIXELFORMATDESCRIPTOR {
+        nSize: std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u16,
+        nVersion: 1,
+        dwFlags: flags,
+        iPixelType: PFD_TYPE_RGBA,
+        cColorBits: format.red_bits + format.green_bits + format.blue_bits,
+        cRedBits: format.red_bits,
+        cRedShift: 0,
+        cGreenBits: format.green_bits,
+        cGreenShift: 0,
+        cBlueBits: format.blue_bits,
+        cBlueShift: 0,
+        cAlphaBits: format.alpha_bits,
+        cAlphaShift: 0,
+        cAccumBits: 0,
+        cAccumRedBits: 0,
+        cAccumGreenBits: 0,
+        cAccumBlueBits: 0,
+        cAccumAlphaBits: 0,
+        cDepthBits: format.depth_bits,
+        cStencilBits: format.stencil_bits,
+        cAuxBuffers: 0,
+        iLayerType: PFD_MAIN_PLANE,
+        bReserved: 0,
+        dwLayerMask: 0,
+        dwVisibleMask: 0,
+        dwDamageMask: 0,
+    }
+}
+
+/// What `window_proc` keeps for each window it was installed on.
+struct WindowState {
+    /// The procedure `window_proc` replaced, which gets every message GLDK doesn't handle.
+    previous: WNDPROC,
+    scale_factor: f64,
+    cursor_inside: bool,
+    minimized: bool,
+    keyboard: KeyboardState,
+    /// The first half of a character outside the BMP, which `WM_CHAR` delivers in two messages.
+    high_surrogate: Option<u16>,
+}
+
+thread_local! {
+    static WINDOWS: RefCell<HashMap<usize, WindowState>> = RefCell::new(HashMap::new());
+    /// Events posted by `window_proc` for every window of the thread.
+    static EVENTS: RefCell<VecDeque<(WindowID, WindowEvent)>> = RefCell::new(VecDeque::new());
+}
+
+fn with_window_state<R>(hwnd: HWND, f: impl FnOnce(&mut WindowState) -> R) -> Option<R> {
+    WINDOWS.with(|windows| windows.borrow_mut().get_mut(&(hwnd as usize)).map(f))
+}
+
+/// Translates the messages GLDK reports into events and passes everything else on to gwl.
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    let push = |event| EVENTS.with(|events| events.borrow_mut().push_back((WindowID(hwnd as u64), event)));
+
+    match msg {
+        WM_PAINT => {
+            ValidateRect(hwnd, null());
+            push(WindowEvent::RedrawRequested);
+            return 0;
+        }
+        // Closing is left to the application.
+        WM_CLOSE => {
+            push(WindowEvent::CloseRequested);
+            return 0;
+        }
+        WM_KEYDOWN | WM_SYSKEYDOWN => {
+            let mut input = keyboard_input(wparam, lparam);
+            let held = with_window_state(hwnd, |state| state.keyboard.press(&input)).unwrap_or(false);
+            // Bit 30 is set when the key was already down, which also covers presses from before
+            // the window had focus.
+            input.repeat = held || lparam & (1 << 30) != 0;
+            push(WindowEvent::Keydown(input));
+        }
+        WM_KEYUP | WM_SYSKEYUP => {
+            let input = keyboard_input(wparam, lparam);
+            with_window_state(hwnd, |state| state.keyboard.release(&input));
+            push(WindowEvent::Keyup(input));
+        }
+        WM_CHAR => {
+            let unit = wparam as u16;
+            if (0xd800..0xdc00).contains(&unit) {
+                with_window_state(hwnd, |state| state.high_surrogate = Some(unit));
+                return 0;
             }
+            let high = with_window_state(hwnd, |state| state.high_surrogate.take()).flatten();
+            for c in char::decode_utf16(high.into_iter().chain([unit])).filter_map(Result::ok) {
+                push(WindowEvent::ReceivedCharacter(c));
+            }
+            return 0;
+        }
+        WM_MOUSEMOVE => {
+            let (entered, scale_factor) = with_window_state(hwnd, |state| {
+                (!std::mem::replace(&mut state.cursor_inside, true), state.scale_factor)
+            })
+            .unwrap_or((false, 1.0));
+            // Win32 only reports leaving, and only when asked to each time the cursor comes back.
+            if entered {
+                let mut track = TRACKMOUSEEVENT {
+                    cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
+                    dwFlags: TME_LEAVE,
+                    hwndTrack: hwnd,
+                    dwHoverTime: 0,
+                };
+                TrackMouseEvent(&mut track);
+                push(WindowEvent::CursorEntered);
+            }
+            let physical = Position {
+                x: (lparam & 0xffff) as i16 as f64,
+                y: ((lparam >> 16) & 0xffff) as i16 as f64,
+            };
+            push(WindowEvent::CursorMoved {
+                physical,
+                logical: physical.to_logical(scale_factor),
+            });
+            return 0;
+        }
+        WM_MOUSELEAVE => {
+            with_window_state(hwnd, |state| state.cursor_inside = false);
+            push(WindowEvent::CursorLeft);
+            return 0;
+        }
+        WM_LBUTTONDOWN => push(WindowEvent::MouseButtonDown(MouseButton::Left)),
+        WM_LBUTTONUP => push(WindowEvent::MouseButtonUp(MouseButton::Left)),
+        WM_RBUTTONDOWN => push(WindowEvent::MouseButtonDown(MouseButton::Right)),
+        WM_RBUTTONUP => push(WindowEvent::MouseButtonUp(MouseButton::Right)),
+        WM_MBUTTONDOWN => push(WindowEvent::MouseButtonDown(MouseButton::Middle)),
+        WM_MBUTTONUP => push(WindowEvent::MouseButtonUp(MouseButton::Middle)),
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+            let delta = GET_WHEEL_DELTA_WPARAM(wparam) as f32 / WHEEL_DELTA as f32;
+            push(WindowEvent::MouseWheel(match msg {
+                WM_MOUSEWHEEL => ScrollDelta::Lines { x: 0.0, y: delta },
+                _ => ScrollDelta::Lines { x: delta, y: 0.0 },
+            }));
+            return 0;
+        }
+        WM_SIZE => {
+            let minimized = wparam == SIZE_MINIMIZED;
+            let was_minimized = with_window_state(hwnd, |state| {
+                std::mem::replace(&mut state.minimized, minimized)
+            })
+            .unwrap_or(false);
+            match (was_minimized, minimized) {
+                (false, true) => push(WindowEvent::Minimized),
+                (true, false) => push(WindowEvent::Restored),
+                _ => {}
+            }
+            // A minimized window's client area is empty.
+            if !minimized {
+                let (width, height) = (LOWORD(lparam as DWORD), HIWORD(lparam as DWORD));
+                push(WindowEvent::Resized(width as u32, height as u32));
+            }
+        }
+        WM_MOVE => {
+            // Minimized windows are moved off screen.
+            if IsIconic(hwnd) == 0 {
+                let x = (lparam & 0xffff) as i16 as i32;
+                let y = ((lparam >> 16) & 0xffff) as i16 as i32;
+                push(WindowEvent::Moved(x, y));
+            }
+        }
+        // The composition is reported as events for the application to draw, instead of in the
+        // IME's own window.
+        WM_IME_SETCONTEXT => {
+            let lparam = lparam & !(ISC_SHOWUICOMPOSITIONWINDOW as LPARAM);
+            return call_previous(hwnd, msg, wparam, lparam);
+        }
+        WM_IME_STARTCOMPOSITION => return 0,
+        WM_IME_COMPOSITION => {
+            let himc = ImmGetContext(hwnd);
+            if himc.is_null() {
+                return 0;
+            }
+            let flags = lparam as DWORD;
+            if flags & GCS_RESULTSTR != 0 {
+                let text = String::from_utf16_lossy(&composition_string(himc, GCS_RESULTSTR));
+                if !text.is_empty() {
+                    push(WindowEvent::ImeCommit(text));
+                }
+            }
+            if flags & GCS_COMPSTR != 0 {
+                let units = composition_string(himc, GCS_COMPSTR);
+                // The caret is counted in UTF-16 units.
+                let cursor = (flags & GCS_CURSORPOS != 0).then(|| {
+                    let caret = ImmGetCompositionStringW(himc, GCS_CURSORPOS, null_mut(), 0).max(0) as usize;
+                    String::from_utf16_lossy(&units[..caret.min(units.len())]).len()
+                });
+                push(WindowEvent::ImePreedit {
+                    text: String::from_utf16_lossy(&units),
+                    cursor,
+                });
+            }
+            ImmReleaseContext(hwnd, himc);
+            // DefWindowProc would send the result again as WM_IME_CHAR.
+            return 0;
+        }
+        WM_IME_ENDCOMPOSITION => push(WindowEvent::ImePreedit {
+            text: String::new(),
+            cursor: None,
+        }),
+        WM_SETFOCUS => push(WindowEvent::Focused(true)),
+        WM_KILLFOCUS => {
+            with_window_state(hwnd, |state| state.keyboard.clear());
+            push(WindowEvent::Focused(false));
+        }
+        WM_XBUTTONDOWN | WM_XBUTTONUP => {
+            let button = match HIWORD(wparam as DWORD) {
+                XBUTTON1 => MouseButton::Back,
+                XBUTTON2 => MouseButton::Forward,
+                other => MouseButton::Other(other),
+            };
+            push(match msg {
+                WM_XBUTTONDOWN => WindowEvent::MouseButtonDown(button),
+                _ => WindowEvent::MouseButtonUp(button),
+            });
+            // Unlike the other button messages, these have to return TRUE when handled.
+            return TRUE as LRESULT;
+        }
+        _ => {}
+    }
+
+    call_previous(hwnd, msg, wparam, lparam)
+}
 
-            let old_ctx = wgl::CreateContext(hdc as wgl::types::HDC);
-            wgl::MakeCurrent(hdc as wgl::types::HDC, old_ctx);
+unsafe fn call_previous(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match with_window_state(hwnd, |state| state.previous) {
+        Some(previous) => CallWindowProcA(previous, hwnd, msg, wparam, lparam),
+        None => DefWindowProcA(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// `index` is `GCS_COMPSTR` or `GCS_RESULTSTR`.
+unsafe fn composition_string(himc: HIMC, index: DWORD) -> Vec<u16> {
+    let size = ImmGetCompositionStringW(himc, index, null_mut(), 0);
+    if size <= 0 {
+        return Vec::new();
+    }
+    let mut units = vec![0u16; size as usize / 2];
+    let size = ImmGetCompositionStringW(himc, index, units.as_mut_ptr() as *mut c_void, size as DWORD);
+    units.truncate(size.max(0) as usize / 2);
+    units
+}
+
+unsafe fn keyboard_input(wparam: WPARAM, lparam: LPARAM) -> KeyboardInput {
+    let extended = lparam & (1 << 24) != 0;
+    let scancode = ((lparam >> 16) & 0xff) as u32 | if extended { 0xe000 } else { 0 };
+
+    // Left and right modifiers share a VK, the scancode or the extended bit tells them apart.
+    let keycode = match wparam as c_int {
+        VK_SHIFT => match MapVirtualKeyA(scancode & 0xff, MAPVK_VSC_TO_VK_EX) as c_int {
+            VK_RSHIFT => VirtualKeyCode::RShift,
+            _ => VirtualKeyCode::LShift,
+        },
+        VK_CONTROL if extended => VirtualKeyCode::RControl,
+        VK_CONTROL => VirtualKeyCode::LControl,
+        VK_MENU if extended => VirtualKeyCode::RAlt,
+        VK_MENU => VirtualKeyCode::LAlt,
+        VK_RETURN if extended => VirtualKeyCode::NumpadEnter,
+        // With NumLock off the keypad sends navigation keys, but without the extended bit the
+        // dedicated ones have. Report the keypad key either way, like X11 does.
+        VK_INSERT if !extended => VirtualKeyCode::Numpad0,
+        VK_END if !extended => VirtualKeyCode::Numpad1,
+        VK_DOWN if !extended => VirtualKeyCode::Numpad2,
+        VK_NEXT if !extended => VirtualKeyCode::Numpad3,
+        VK_LEFT if !extended => VirtualKeyCode::Numpad4,
+        VK_CLEAR if !extended => VirtualKeyCode::Numpad5,
+        VK_RIGHT if !extended => VirtualKeyCode::Numpad6,
+        VK_HOME if !extended => VirtualKeyCode::Numpad7,
+        VK_UP if !extended => VirtualKeyCode::Numpad8,
+        VK_PRIOR if !extended => VirtualKeyCode::Numpad9,
+        VK_DELETE if !extended => VirtualKeyCode::NumpadDecimal,
+        vk => virtual_keycode(vk),
+    };
+
+    let down = |vk| GetKeyState(vk) < 0;
+    let toggled = |vk| GetKeyState(vk) & 1 != 0;
+    let modifiers = Modifiers {
+        shift: down(VK_SHIFT),
+        ctrl: down(VK_CONTROL),
+        alt: down(VK_MENU),
+        super_key: down(VK_LWIN) || down(VK_RWIN),
+        caps_lock: toggled(VK_CAPITAL),
+        num_lock: toggled(VK_NUMLOCK),
+    };
+
+    KeyboardInput {
+        keycode,
+        scancode,
+        modifiers,
+        // Only the window's keyboard state knows.
+        repeat: false,
+    }
+}
+
+fn virtual_keycode(vk: c_int) -> VirtualKeyCode {
+    match vk {
+        // Digits and letters are their ASCII codes.
+        0x30..=0x39 => VirtualKeyCode::digit((vk - 0x30) as u32),
+        0x41..=0x5a => VirtualKeyCode::letter((vk - 0x41) as u32),
+        VK_F1..=VK_F24 => VirtualKeyCode::function_key((vk - VK_F1) as u32),
+        VK_NUMPAD0..=VK_NUMPAD9 => VirtualKeyCode::numpad_digit((vk - VK_NUMPAD0) as u32),
 
-            let att = [
-                wgl_extra::CONTEXT_MAJOR_VERSION_ARB,
-                major,
-                wgl_extra::CONTEXT_MINOR_VERSION_ARB,
-                minor,
-                wgl_extra::CONTEXT_FLAGS_ARB,
-                0,
-                wgl_extra::CONTEXT_PROFILE_MASK_ARB,
-                wgl_extra::CONTEXT_CORE_PROFILE_BIT_ARB,
-                0,
-            ];
+        VK_ESCAPE => VirtualKeyCode::Escape,
+        VK_SNAPSHOT => VirtualKeyCode::PrintScreen,
+        VK_SCROLL => VirtualKeyCode::ScrollLock,
+        VK_PAUSE => VirtualKeyCode::Pause,
 
-            let func = crate::sys::WGLARBFunctions::load();
-            let ctx =
-                (func.wglCreateContextAttribsARB)(hdc as wgl_extra::types::HDC, null_mut(), &att);
+        VK_INSERT => VirtualKeyCode::Insert,
+        VK_DELETE => VirtualKeyCode::Delete,
+        VK_HOME => VirtualKeyCode::Home,
+        VK_END => VirtualKeyCode::End,
+        VK_PRIOR => VirtualKeyCode::PageUp,
+        VK_NEXT => VirtualKeyCode::PageDown,
+
+        VK_LEFT => VirtualKeyCode::Left,
+        VK_UP => VirtualKeyCode::Up,
+        VK_RIGHT => VirtualKeyCode::Right,
+        VK_DOWN => VirtualKeyCode::Down,
+
+        VK_BACK => VirtualKeyCode::Backspace,
+        VK_RETURN => VirtualKeyCode::Return,
+        VK_SPACE => VirtualKeyCode::Space,
+        VK_TAB => VirtualKeyCode::Tab,
+        VK_CAPITAL => VirtualKeyCode::CapsLock,
+        VK_APPS => VirtualKeyCode::Menu,
+
+        VK_ADD => VirtualKeyCode::NumpadAdd,
+        VK_SUBTRACT => VirtualKeyCode::NumpadSubtract,
+        VK_MULTIPLY => VirtualKeyCode::NumpadMultiply,
+        VK_DIVIDE => VirtualKeyCode::NumpadDivide,
+        VK_DECIMAL => VirtualKeyCode::NumpadDecimal,
+        VK_NUMLOCK => VirtualKeyCode::NumLock,
+
+        VK_LWIN => VirtualKeyCode::LSuper,
+        VK_RWIN => VirtualKeyCode::RSuper,
+
+        VK_OEM_3 => VirtualKeyCode::Grave,
+        VK_OEM_MINUS => VirtualKeyCode::Minus,
+        VK_OEM_PLUS => VirtualKeyCode::Equals,
+        VK_OEM_4 => VirtualKeyCode::LBracket,
+        VK_OEM_6 => VirtualKeyCode::RBracket,
+        VK_OEM_5 => VirtualKeyCode::Backslash,
+        VK_OEM_1 => VirtualKeyCode::Semicolon,
+        VK_OEM_7 => VirtualKeyCode::Apostrophe,
+        VK_OEM_COMMA => VirtualKeyCode::Comma,
+        VK_OEM_PERIOD => VirtualKeyCode::Period,
+        VK_OEM_2 => VirtualKeyCode::Slash,
+
+        _ => VirtualKeyCode::Unknown,
+    }
+}
+
+/// Every window of a thread shares its message queue, so there is nothing to connect to.
+pub struct REventLoop;
+
+impl REventLoop {
+    pub fn new() -> Result<Self, GLDKError> {
+        Ok(Self)
+    }
 
-            (*self.props).ctx = Some(ctx);
-            (*self.props).wgl = Some(func);
+    pub fn create_window(
+        &self,
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: GLConfig,
+        share: Option<&RWindow>,
+    ) -> Result<RWindow, GLDKError> {
+        RWindow::new(width, height, title, conf, share)
+    }
 
-            wgl::DeleteContext(old_ctx);
+    /// Returns the next queued event, blocking until a message arrives if there is none, for at most
+    /// `timeout` unless it is `None`.
+    pub fn wait_event(&self, timeout: Option<Duration>) -> Result<Option<(WindowID, WindowEvent)>, GLDKError> {
+        if let Some(event) = EVENTS.with(|events| events.borrow_mut().pop_front()) {
+            return Ok(Some(event));
+        }
+        unsafe {
+            let mut msg: MSG = std::mem::zeroed();
+            if let Some(timeout) = timeout {
+                let peek = |msg: &mut MSG| PeekMessageA(msg, null_mut(), 0, 0, PM_REMOVE) != 0;
+                if !peek(&mut msg) {
+                    // Rounded up so short waits don't spin, and short of INFINITE.
+                    let millis = timeout.as_micros().div_ceil(1000).min(DWORD::MAX as u128 - 1) as DWORD;
+                    MsgWaitForMultipleObjects(0, null(), FALSE, millis, QS_ALLINPUT);
+                    if !peek(&mut msg) {
+                        return Ok(None);
+                    }
+                }
+            } else if GetMessageA(&mut msg, null_mut(), 0, 0) == -1 {
+                return Err(GLDKError::new_unexpected("GetMessage failed.".to_owned()));
+            }
+            TranslateMessage(&msg);
+            DispatchMessageA(&msg);
         }
+        Ok(EVENTS.with(|events| events.borrow_mut().pop_front()))
     }
 }
 
 pub struct RWindow {
     props: Props,
     inner: Window,
+    running: Cell<bool>,
 }
 
 impl RWindow {
-    pub fn new(width: u32, height: u32, title: &str, conf: GLConfig) -> Result<Self,GLDKError> {
+    pub fn new(
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: GLConfig,
+        share: Option<&RWindow>,
+    ) -> Result<Self, GLDKError> {
+        if conf.backend == GLContextBackend::Egl {
+            return Err(GLDKError::new_simple(ErrorKind::Unsupported("EGL".to_owned())));
+        }
+
         let mut props = Props {
             hwnd: None,
             hinstance: None,
             wgl: None,
             ctx: None,
+            version: conf.version,
+            info: None,
+            format: conf.framebuffer,
         };
 
         let mut action = BuildAction {
             conf,
+            share: share.and_then(|share| share.props.ctx),
             props: addr_of_mut!(props),
             error: None,
         };
@@ -163,21 +848,50 @@ impl RWindow {
             }
         }
 
-        Ok(Self { props, inner })
+        let hwnd = inner.get_instance().hwnd;
+        unsafe {
+            let hdc = GetDC(hwnd);
+            let scale_factor = GetDeviceCaps(hdc, LOGPIXELSX) as f64 / 96.0;
+            ReleaseDC(hwnd, hdc);
+
+            let previous = SetWindowLongPtrA(hwnd, GWLP_WNDPROC, window_proc as usize as _);
+            let state = WindowState {
+                previous: std::mem::transmute::<_, WNDPROC>(previous),
+                scale_factor,
+                cursor_inside: false,
+                minimized: false,
+                keyboard: KeyboardState::default(),
+                high_surrogate: None,
+            };
+            WINDOWS.with(|windows| windows.borrow_mut().insert(hwnd as usize, state));
+        }
+
+        Ok(Self {
+            props,
+            inner,
+            running: Cell::new(false),
+        })
     }
 
     pub fn get_proc_address(&self, addr: &str) -> *const c_void {
-        let addr = CString::new(addr.as_bytes()).unwrap();
-        let addr = addr.as_ptr();
+        get_proc_address(addr)
+    }
 
-        unsafe {
-            let p = wgl::GetProcAddress(addr) as *const core::ffi::c_void;
-            if !p.is_null() {
-                return p;
-            }
-            let gl = GetModuleHandleA("Opengl32.dll".as_ptr() as *const i8);
-            GetProcAddress(gl, addr as PCSTR) as *const _
-        }
+    pub fn get_context_info(&self) -> &ContextInfo {
+        self.props.info.as_ref().unwrap()
+    }
+
+    pub fn get_gl_version(&self) -> GLVersion {
+        self.props.version
+    }
+
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        self.props.format
+    }
+
+    /// From the DPI the window was created at.
+    pub fn scale_factor(&self) -> f64 {
+        with_window_state(self.inner.get_instance().hwnd, |state| state.scale_factor).unwrap_or(1.0)
     }
 
     pub fn handle(&self) -> RawWindowHandle {
@@ -188,6 +902,10 @@ impl RWindow {
         RawWindowHandle::Win32(window_handle)
     }
 
+    pub fn display_handle(&self) -> RawDisplayHandle {
+        RawDisplayHandle::Windows(WindowsDisplayHandle::empty())
+    }
+
     pub fn id(&self) -> WindowID {
         WindowID(self.inner.get_instance().hwnd as u64)
     }
@@ -208,22 +926,27 @@ impl RWindow {
         (wgl.wglSwapIntervalEXT)(enable as u32);
     }
 
-    pub fn run<F>(&self, mut callback: F)
+    pub fn run<F>(&self, mut callback: F) -> i32
     where
-        F: FnMut(WindowEvent),
+        F: FnMut(WindowEvent, &mut ControlFlow),
     {
-        self.inner.run(|event, _control_flow| match event {
-            gwl::window::WindowEvent::Expose => {
-                callback(WindowEvent::RedrawRequested);
+        let id = self.id();
+        let mut control_flow = ControlFlow::default();
+        self.running.set(true);
+        loop {
+            if let ControlFlow::Exit(code) = control_flow {
+                return code;
             }
-            gwl::window::WindowEvent::KeyDown(c) =>  {
-                callback(WindowEvent::Keydown(KeyCode(c)))
-            },
-            gwl::window::WindowEvent::KeyUp(c) => callback(WindowEvent::Keyup(KeyCode(c))),
-            gwl::window::WindowEvent::CloseRequested => {
-                callback(WindowEvent::CloseRequested)
+            if !self.running.get() {
+                return 0;
             }
-        });
+            match REventLoop.wait_event(control_flow.timeout()) {
+                Ok(Some((event_id, event))) if event_id == id => callback(event, &mut control_flow),
+                Ok(None) if control_flow.is_due() => callback(WindowEvent::RedrawRequested, &mut control_flow),
+                Ok(_) => {}
+                Err(_) => return 1,
+            }
+        }
     }
 
     pub fn swap_buffers(&self) {
@@ -269,9 +992,70 @@ impl RWindow {
         self.inner.set_minimized(minimized);
     }
 
+    pub fn keyboard_state(&self) -> KeyboardState {
+        with_window_state(self.inner.get_instance().hwnd, |state| state.keyboard.clone()).unwrap_or_default()
+    }
+
+    /// Disabling detaches the window from its input context, enabling gives it the default one back.
+    pub fn set_ime_enabled(&self, enabled: bool) {
+        let flags = if enabled { IACE_DEFAULT } else { 0 };
+        unsafe {
+            ImmAssociateContextEx(self.inner.get_instance().hwnd, null_mut(), flags);
+        }
+    }
+
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        let hwnd = self.inner.get_instance().hwnd;
+        let position = POINT { x, y };
+        let area = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        unsafe {
+            let himc = ImmGetContext(hwnd);
+            if himc.is_null() {
+                return;
+            }
+            let mut composition = COMPOSITIONFORM {
+                dwStyle: CFS_POINT,
+                ptCurrentPos: position,
+                rcArea: area,
+            };
+            ImmSetCompositionWindow(himc, &mut composition);
+            let mut candidate = CANDIDATEFORM {
+                dwIndex: 0,
+                dwStyle: CFS_CANDIDATEPOS,
+                ptCurrentPos: position,
+                rcArea: area,
+            };
+            ImmSetCandidateWindow(himc, &mut candidate);
+            ImmReleaseContext(hwnd, himc);
+        }
+    }
+
     pub fn quit(&self) {
+        self.running.set(false);
+    }
+}
+
+impl Drop for RWindow {
+    fn drop(&mut self) {
         unsafe {
-            PostQuitMessage(0);
+            if let Some(ctx) = self.props.ctx {
+                if wgl::GetCurrentContext() == ctx as wgl::types::HGLRC {
+                    wgl::MakeCurrent(null(), null());
+                }
+                wgl::DeleteContext(ctx as wgl::types::HGLRC);
+            }
+            if let Some(hwnd) = self.props.hwnd {
+                // Without its previous procedure the window is torn down by DefWindowProc.
+                WINDOWS.with(|windows| windows.borrow_mut().remove(&(hwnd as usize)));
+                DestroyWindow(hwnd);
+                let id = WindowID(hwnd as u64);
+                EVENTS.with(|events| events.borrow_mut().retain(|(event_id, _)| *event_id != id));
+            }
         }
     }
 }
@@ -1 +1,116 @@
+use std::mem::transmute;
+use std::os::raw::{c_char, c_int, c_short, c_uint, c_ulong};
+use x11::glx::{glXGetProcAddressARB, GLXContext, GLXDrawable, GLXFBConfig};
+use x11::xlib::{Bool, Display};
 
+#[allow(non_camel_case_types, non_upper_case_globals, unused_imports, clippy::all)]
+pub mod egl {
+    use std::os::raw::c_void;
+
+    pub type khronos_utime_nanoseconds_t = u64;
+    pub type khronos_uint64_t = u64;
+    pub type khronos_ssize_t = isize;
+    pub type EGLint = i32;
+    pub type EGLNativeDisplayType = *const c_void;
+    pub type EGLNativePixmapType = *const c_void;
+    pub type EGLNativeWindowType = *const c_void;
+    pub type NativeDisplayType = EGLNativeDisplayType;
+    pub type NativePixmapType = EGLNativePixmapType;
+    pub type NativeWindowType = EGLNativeWindowType;
+
+    include!(concat!(env!("OUT_DIR"), "/egl_bindings.rs"));
+
+    #[link(name = "EGL")]
+    extern "C" {}
+
+    // EGL_KHR_platform_x11
+    pub const PLATFORM_X11_KHR: types::EGLenum = 0x31D5;
+
+    // EGL_KHR_platform_wayland
+    #[cfg(feature = "wayland")]
+    pub const PLATFORM_WAYLAND_KHR: types::EGLenum = 0x31D8;
+
+    // EGL_MESA_platform_surfaceless
+    pub const PLATFORM_SURFACELESS_MESA: types::EGLenum = 0x31DD;
+
+    // EGL_KHR_create_context_no_error
+    pub const CONTEXT_OPENGL_NO_ERROR_KHR: types::EGLenum = 0x31B3;
+}
+
+// GLX_EXT_create_context_es2_profile
+pub const GLX_CONTEXT_ES2_PROFILE_BIT_EXT: c_int = 0x0004;
+
+// GLX_ARB_framebuffer_sRGB / GLX_EXT_framebuffer_sRGB
+pub const GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB: c_int = 0x20B2;
+
+// GLX_ARB_create_context_robustness
+pub const GLX_CONTEXT_ROBUST_ACCESS_BIT_ARB: c_int = 0x0004;
+pub const GLX_CONTEXT_RESET_NOTIFICATION_STRATEGY_ARB: c_int = 0x8256;
+pub const GLX_NO_RESET_NOTIFICATION_ARB: c_int = 0x8261;
+pub const GLX_LOSE_CONTEXT_ON_RESET_ARB: c_int = 0x8252;
+
+// GLX_ARB_create_context_no_error
+pub const GLX_CONTEXT_OPENGL_NO_ERROR_ARB: c_int = 0x31B3;
+
+// <locale.h>
+pub const LC_CTYPE: c_int = 0;
+
+// <poll.h>
+pub const POLLIN: c_short = 0x001;
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub struct pollfd {
+    pub fd: c_int,
+    pub events: c_short,
+    pub revents: c_short,
+}
+
+extern "C" {
+    pub fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+    pub fn poll(fds: *mut pollfd, count: c_ulong, timeout: c_int) -> c_int;
+}
+
+#[allow(clippy::upper_case_acronyms)]
+pub type GLXCREATECONTEXTATTRIBSARBPROC = unsafe extern "C" fn(
+    dpy: *mut Display,
+    config: GLXFBConfig,
+    share_context: GLXContext,
+    direct: Bool,
+    attrib_list: *const c_int,
+) -> GLXContext;
+
+#[allow(clippy::upper_case_acronyms)]
+pub type GLXSWAPINTERVALEXTPROC =
+    unsafe extern "C" fn(dpy: *mut Display, drawable: GLXDrawable, interval: c_int);
+
+#[allow(clippy::upper_case_acronyms)]
+pub type GLXSWAPINTERVALMESAPROC = unsafe extern "C" fn(interval: c_uint) -> c_int;
+
+/// Functions that are not necessarily always available
+#[allow(non_snake_case)]
+pub struct GLXARBFunctions {
+    pub glXCreateContextAttribsARB: Option<GLXCREATECONTEXTATTRIBSARBPROC>,
+    pub glXSwapIntervalEXT: Option<GLXSWAPINTERVALEXTPROC>,
+    pub glXSwapIntervalMESA: Option<GLXSWAPINTERVALMESAPROC>,
+}
+
+impl GLXARBFunctions {
+    #[allow(non_snake_case)]
+    pub fn load() -> Self {
+        unsafe {
+            let glXCreateContextAttribsARB =
+                glXGetProcAddressARB(c"glXCreateContextAttribsARB".as_ptr() as *const u8)
+                    .map(|f| transmute::<_, GLXCREATECONTEXTATTRIBSARBPROC>(f));
+            let glXSwapIntervalEXT = glXGetProcAddressARB(c"glXSwapIntervalEXT".as_ptr() as *const u8)
+                .map(|f| transmute::<_, GLXSWAPINTERVALEXTPROC>(f));
+            let glXSwapIntervalMESA = glXGetProcAddressARB(c"glXSwapIntervalMESA".as_ptr() as *const u8)
+                .map(|f| transmute::<_, GLXSWAPINTERVALMESAPROC>(f));
+            Self {
+                glXCreateContextAttribsARB,
+                glXSwapIntervalEXT,
+                glXSwapIntervalMESA,
+            }
+        }
+    }
+}
@@ -2,6 +2,10 @@
 pub mod linux;
 #[cfg(target_os = "linux")]
 pub use self::linux::*;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+pub mod wayland;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+pub mod xkbcommon;
 
 #[cfg(target_os = "macos")]
 pub mod macos;
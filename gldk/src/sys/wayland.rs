@@ -0,0 +1,345 @@
+//! Bindings to libwayland-client and libwayland-egl, and the parts of the xdg-shell protocol
+//! that wayland-scanner would otherwise generate.
+#![allow(non_camel_case_types, non_upper_case_globals)]
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr::{addr_of, null};
+
+pub enum wl_display {}
+pub enum wl_proxy {}
+pub enum wl_egl_window {}
+
+#[repr(C)]
+pub struct wl_message {
+    pub name: *const c_char,
+    pub signature: *const c_char,
+    pub types: *const *const wl_interface,
+}
+
+#[repr(C)]
+pub struct wl_interface {
+    pub name: *const c_char,
+    pub version: c_int,
+    pub method_count: c_int,
+    pub methods: *const wl_message,
+    pub event_count: c_int,
+    pub events: *const wl_message,
+}
+
+unsafe impl Sync for wl_message {}
+unsafe impl Sync for wl_interface {}
+
+#[repr(C)]
+pub struct wl_array {
+    pub size: usize,
+    pub alloc: usize,
+    pub data: *mut c_void,
+}
+
+pub const WL_MARSHAL_FLAG_DESTROY: u32 = 1;
+
+#[link(name = "wayland-client")]
+extern "C" {
+    pub static wl_registry_interface: wl_interface;
+    pub static wl_compositor_interface: wl_interface;
+    pub static wl_surface_interface: wl_interface;
+    pub static wl_seat_interface: wl_interface;
+    pub static wl_pointer_interface: wl_interface;
+    pub static wl_keyboard_interface: wl_interface;
+    pub static wl_output_interface: wl_interface;
+
+    pub fn wl_display_connect(name: *const c_char) -> *mut wl_display;
+    pub fn wl_display_disconnect(display: *mut wl_display);
+    pub fn wl_display_dispatch(display: *mut wl_display) -> c_int;
+    pub fn wl_display_roundtrip(display: *mut wl_display) -> c_int;
+    pub fn wl_display_flush(display: *mut wl_display) -> c_int;
+    pub fn wl_display_get_fd(display: *mut wl_display) -> c_int;
+    pub fn wl_display_prepare_read(display: *mut wl_display) -> c_int;
+    pub fn wl_display_read_events(display: *mut wl_display) -> c_int;
+    pub fn wl_display_cancel_read(display: *mut wl_display);
+    pub fn wl_display_dispatch_pending(display: *mut wl_display) -> c_int;
+
+    pub fn wl_proxy_marshal_flags(
+        proxy: *mut wl_proxy,
+        opcode: u32,
+        interface: *const wl_interface,
+        version: u32,
+        flags: u32,
+        ...
+    ) -> *mut wl_proxy;
+    pub fn wl_proxy_add_listener(
+        proxy: *mut wl_proxy,
+        implementation: *const c_void,
+        data: *mut c_void,
+    ) -> c_int;
+    pub fn wl_proxy_get_version(proxy: *mut wl_proxy) -> u32;
+    pub fn wl_proxy_get_user_data(proxy: *mut wl_proxy) -> *mut c_void;
+    pub fn wl_proxy_destroy(proxy: *mut wl_proxy);
+}
+
+#[link(name = "wayland-egl")]
+extern "C" {
+    pub fn wl_egl_window_create(surface: *mut wl_proxy, width: c_int, height: c_int) -> *mut wl_egl_window;
+    pub fn wl_egl_window_destroy(window: *mut wl_egl_window);
+    pub fn wl_egl_window_resize(window: *mut wl_egl_window, width: c_int, height: c_int, dx: c_int, dy: c_int);
+}
+
+// wl_display
+pub const WL_DISPLAY_GET_REGISTRY: u32 = 1;
+
+// wl_registry
+pub const WL_REGISTRY_BIND: u32 = 0;
+
+// wl_compositor
+pub const WL_COMPOSITOR_CREATE_SURFACE: u32 = 0;
+
+// wl_surface
+pub const WL_SURFACE_DESTROY: u32 = 0;
+pub const WL_SURFACE_COMMIT: u32 = 6;
+pub const WL_SURFACE_SET_BUFFER_SCALE: u32 = 8;
+
+// wl_seat
+pub const WL_SEAT_GET_POINTER: u32 = 0;
+pub const WL_SEAT_GET_KEYBOARD: u32 = 1;
+pub const WL_SEAT_RELEASE: u32 = 3;
+pub const WL_SEAT_CAPABILITY_POINTER: u32 = 1;
+pub const WL_SEAT_CAPABILITY_KEYBOARD: u32 = 2;
+
+// wl_pointer
+pub const WL_POINTER_RELEASE: u32 = 1;
+pub const WL_POINTER_BUTTON_STATE_PRESSED: u32 = 1;
+pub const WL_POINTER_AXIS_VERTICAL_SCROLL: u32 = 0;
+
+// wl_keyboard
+pub const WL_KEYBOARD_RELEASE: u32 = 0;
+pub const WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1: u32 = 1;
+pub const WL_KEYBOARD_KEY_STATE_PRESSED: u32 = 1;
+
+// xdg_wm_base
+pub const XDG_WM_BASE_DESTROY: u32 = 0;
+pub const XDG_WM_BASE_GET_XDG_SURFACE: u32 = 2;
+pub const XDG_WM_BASE_PONG: u32 = 3;
+
+// xdg_surface
+pub const XDG_SURFACE_DESTROY: u32 = 0;
+pub const XDG_SURFACE_GET_TOPLEVEL: u32 = 1;
+pub const XDG_SURFACE_ACK_CONFIGURE: u32 = 4;
+
+// xdg_toplevel
+pub const XDG_TOPLEVEL_DESTROY: u32 = 0;
+pub const XDG_TOPLEVEL_SET_TITLE: u32 = 2;
+pub const XDG_TOPLEVEL_SET_MAXIMIZED: u32 = 9;
+pub const XDG_TOPLEVEL_UNSET_MAXIMIZED: u32 = 10;
+pub const XDG_TOPLEVEL_SET_MINIMIZED: u32 = 13;
+pub const XDG_TOPLEVEL_STATE_ACTIVATED: u32 = 4;
+
+#[repr(C)]
+pub struct wl_registry_listener {
+    pub global: unsafe extern "C" fn(
+        data: *mut c_void,
+        registry: *mut wl_proxy,
+        name: u32,
+        interface: *const c_char,
+        version: u32,
+    ),
+    pub global_remove: unsafe extern "C" fn(data: *mut c_void, registry: *mut wl_proxy, name: u32),
+}
+
+pub type wl_fixed_t = i32;
+
+pub fn wl_fixed_to_double(value: wl_fixed_t) -> f64 {
+    value as f64 / 256.0
+}
+
+#[repr(C)]
+pub struct wl_surface_listener {
+    pub enter: unsafe extern "C" fn(data: *mut c_void, surface: *mut wl_proxy, output: *mut wl_proxy),
+    pub leave: unsafe extern "C" fn(data: *mut c_void, surface: *mut wl_proxy, output: *mut wl_proxy),
+}
+
+#[repr(C)]
+pub struct wl_output_listener {
+    pub geometry: unsafe extern "C" fn(
+        data: *mut c_void,
+        output: *mut wl_proxy,
+        x: i32,
+        y: i32,
+        physical_width: i32,
+        physical_height: i32,
+        subpixel: i32,
+        make: *const c_char,
+        model: *const c_char,
+        transform: i32,
+    ),
+    pub mode: unsafe extern "C" fn(data: *mut c_void, output: *mut wl_proxy, flags: u32, width: i32, height: i32, refresh: i32),
+    pub done: unsafe extern "C" fn(data: *mut c_void, output: *mut wl_proxy),
+    pub scale: unsafe extern "C" fn(data: *mut c_void, output: *mut wl_proxy, factor: i32),
+}
+
+#[repr(C)]
+pub struct wl_seat_listener {
+    pub capabilities: unsafe extern "C" fn(data: *mut c_void, seat: *mut wl_proxy, capabilities: u32),
+    pub name: unsafe extern "C" fn(data: *mut c_void, seat: *mut wl_proxy, name: *const c_char),
+}
+
+/// Up to version 5.
+#[repr(C)]
+pub struct wl_pointer_listener {
+    pub enter: unsafe extern "C" fn(
+        data: *mut c_void,
+        pointer: *mut wl_proxy,
+        serial: u32,
+        surface: *mut wl_proxy,
+        x: wl_fixed_t,
+        y: wl_fixed_t,
+    ),
+    pub leave: unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy, serial: u32, surface: *mut wl_proxy),
+    pub motion: unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy, time: u32, x: wl_fixed_t, y: wl_fixed_t),
+    pub button:
+        unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy, serial: u32, time: u32, button: u32, state: u32),
+    pub axis: unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy, time: u32, axis: u32, value: wl_fixed_t),
+    pub frame: unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy),
+    pub axis_source: unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy, source: u32),
+    pub axis_stop: unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy, time: u32, axis: u32),
+    pub axis_discrete: unsafe extern "C" fn(data: *mut c_void, pointer: *mut wl_proxy, axis: u32, discrete: i32),
+}
+
+#[repr(C)]
+pub struct wl_keyboard_listener {
+    pub keymap: unsafe extern "C" fn(data: *mut c_void, keyboard: *mut wl_proxy, format: u32, fd: c_int, size: u32),
+    pub enter: unsafe extern "C" fn(
+        data: *mut c_void,
+        keyboard: *mut wl_proxy,
+        serial: u32,
+        surface: *mut wl_proxy,
+        keys: *mut wl_array,
+    ),
+    pub leave: unsafe extern "C" fn(data: *mut c_void, keyboard: *mut wl_proxy, serial: u32, surface: *mut wl_proxy),
+    pub key:
+        unsafe extern "C" fn(data: *mut c_void, keyboard: *mut wl_proxy, serial: u32, time: u32, key: u32, state: u32),
+    pub modifiers: unsafe extern "C" fn(
+        data: *mut c_void,
+        keyboard: *mut wl_proxy,
+        serial: u32,
+        depressed: u32,
+        latched: u32,
+        locked: u32,
+        group: u32,
+    ),
+    pub repeat_info: unsafe extern "C" fn(data: *mut c_void, keyboard: *mut wl_proxy, rate: i32, delay: i32),
+}
+
+#[repr(C)]
+pub struct xdg_wm_base_listener {
+    pub ping: unsafe extern "C" fn(data: *mut c_void, wm_base: *mut wl_proxy, serial: u32),
+}
+
+#[repr(C)]
+pub struct xdg_surface_listener {
+    pub configure: unsafe extern "C" fn(data: *mut c_void, surface: *mut wl_proxy, serial: u32),
+}
+
+#[repr(C)]
+pub struct xdg_toplevel_listener {
+    pub configure: unsafe extern "C" fn(
+        data: *mut c_void,
+        toplevel: *mut wl_proxy,
+        width: i32,
+        height: i32,
+        states: *mut wl_array,
+    ),
+    pub close: unsafe extern "C" fn(data: *mut c_void, toplevel: *mut wl_proxy),
+}
+
+// xdg-shell, version 1 of every interface. xdg_positioner and xdg_popup are never created,
+// so their slots in the type tables are left null.
+
+#[repr(transparent)]
+struct Types<const N: usize>([*const wl_interface; N]);
+
+unsafe impl<const N: usize> Sync for Types<N> {}
+
+static NO_TYPES: Types<4> = Types([null(); 4]);
+static XDG_WM_BASE_GET_XDG_SURFACE_TYPES: Types<2> =
+    Types([addr_of!(xdg_surface_interface), addr_of!(wl_surface_interface)]);
+static XDG_SURFACE_GET_TOPLEVEL_TYPES: Types<1> = Types([addr_of!(xdg_toplevel_interface)]);
+static XDG_TOPLEVEL_SET_PARENT_TYPES: Types<1> = Types([addr_of!(xdg_toplevel_interface)]);
+static SEAT_TYPES: Types<4> = Types([addr_of!(wl_seat_interface), null(), null(), null()]);
+static OUTPUT_TYPES: Types<1> = Types([addr_of!(wl_output_interface)]);
+
+const fn message<const N: usize>(name: &'static CStr, signature: &'static CStr, types: &'static Types<N>) -> wl_message {
+    wl_message {
+        name: name.as_ptr(),
+        signature: signature.as_ptr(),
+        types: types.0.as_ptr(),
+    }
+}
+
+static XDG_WM_BASE_REQUESTS: [wl_message; 4] = [
+    message(c"destroy", c"", &NO_TYPES),
+    message(c"create_positioner", c"n", &NO_TYPES),
+    message(c"get_xdg_surface", c"no", &XDG_WM_BASE_GET_XDG_SURFACE_TYPES),
+    message(c"pong", c"u", &NO_TYPES),
+];
+
+static XDG_WM_BASE_EVENTS: [wl_message; 1] = [message(c"ping", c"u", &NO_TYPES)];
+
+pub static xdg_wm_base_interface: wl_interface = wl_interface {
+    name: c"xdg_wm_base".as_ptr(),
+    version: 1,
+    method_count: 4,
+    methods: XDG_WM_BASE_REQUESTS.as_ptr(),
+    event_count: 1,
+    events: XDG_WM_BASE_EVENTS.as_ptr(),
+};
+
+static XDG_SURFACE_REQUESTS: [wl_message; 5] = [
+    message(c"destroy", c"", &NO_TYPES),
+    message(c"get_toplevel", c"n", &XDG_SURFACE_GET_TOPLEVEL_TYPES),
+    message(c"get_popup", c"n?oo", &NO_TYPES),
+    message(c"set_window_geometry", c"iiii", &NO_TYPES),
+    message(c"ack_configure", c"u", &NO_TYPES),
+];
+
+static XDG_SURFACE_EVENTS: [wl_message; 1] = [message(c"configure", c"u", &NO_TYPES)];
+
+pub static xdg_surface_interface: wl_interface = wl_interface {
+    name: c"xdg_surface".as_ptr(),
+    version: 1,
+    method_count: 5,
+    methods: XDG_SURFACE_REQUESTS.as_ptr(),
+    event_count: 1,
+    events: XDG_SURFACE_EVENTS.as_ptr(),
+};
+
+static XDG_TOPLEVEL_REQUESTS: [wl_message; 14] = [
+    message(c"destroy", c"", &NO_TYPES),
+    message(c"set_parent", c"?o", &XDG_TOPLEVEL_SET_PARENT_TYPES),
+    message(c"set_title", c"s", &NO_TYPES),
+    message(c"set_app_id", c"s", &NO_TYPES),
+    message(c"show_window_menu", c"ouii", &SEAT_TYPES),
+    message(c"move", c"ou", &SEAT_TYPES),
+    message(c"resize", c"ouu", &SEAT_TYPES),
+    message(c"set_max_size", c"ii", &NO_TYPES),
+    message(c"set_min_size", c"ii", &NO_TYPES),
+    message(c"set_maximized", c"", &NO_TYPES),
+    message(c"unset_maximized", c"", &NO_TYPES),
+    message(c"set_fullscreen", c"?o", &OUTPUT_TYPES),
+    message(c"unset_fullscreen", c"", &NO_TYPES),
+    message(c"set_minimized", c"", &NO_TYPES),
+];
+
+static XDG_TOPLEVEL_EVENTS: [wl_message; 2] = [
+    message(c"configure", c"iia", &NO_TYPES),
+    message(c"close", c"", &NO_TYPES),
+];
+
+pub static xdg_toplevel_interface: wl_interface = wl_interface {
+    name: c"xdg_toplevel".as_ptr(),
+    version: 1,
+    method_count: 14,
+    methods: XDG_TOPLEVEL_REQUESTS.as_ptr(),
+    event_count: 2,
+    events: XDG_TOPLEVEL_EVENTS.as_ptr(),
+};
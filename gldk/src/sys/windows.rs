@@ -9,22 +9,76 @@ pub mod wgl_extra {
     include!(concat!(env!("OUT_DIR"), "/wgl_extra_bindings.rs"));
 }
 
-pub type WGLCREATECONTEXTATTRIBSARBPROC = fn(
+pub type WGLCREATECONTEXTATTRIBSARBPROC = unsafe extern "system" fn(
     hDc: wgl_extra::types::HDC,
     hShareContext: wgl_extra::types::HGLRC,
-    attribs: &[wgl_extra::types::GLenum],
+    attribs: *const wgl_extra::types::GLenum,
 ) -> wgl_extra::types::HGLRC;
 
 pub type WGLSWAPINTERVALEXTPROC = fn(i:u32);
 
+pub type WGLGETEXTENSIONSSTRINGARBPROC =
+    unsafe extern "system" fn(hdc: wgl_extra::types::HDC) -> *const std::os::raw::c_char;
+
+pub type WGLCHOOSEPIXELFORMATARBPROC = unsafe extern "system" fn(
+    hdc: wgl_extra::types::HDC,
+    piAttribIList: *const std::os::raw::c_int,
+    pfAttribFList: *const f32,
+    nMaxFormats: u32,
+    piFormats: *mut std::os::raw::c_int,
+    nNumFormats: *mut u32,
+) -> wgl_extra::types::BOOL;
+
+pub type WGLGETPIXELFORMATATTRIBIVARBPROC = unsafe extern "system" fn(
+    hdc: wgl_extra::types::HDC,
+    iPixelFormat: std::os::raw::c_int,
+    iLayerPlane: std::os::raw::c_int,
+    nAttributes: u32,
+    piAttributes: *const std::os::raw::c_int,
+    piValues: *mut std::os::raw::c_int,
+) -> wgl_extra::types::BOOL;
+
+pub type WGLCREATEPBUFFERARBPROC = unsafe extern "system" fn(
+    hDC: wgl_extra::types::HDC,
+    iPixelFormat: std::os::raw::c_int,
+    iWidth: std::os::raw::c_int,
+    iHeight: std::os::raw::c_int,
+    piAttribList: *const std::os::raw::c_int,
+) -> wgl_extra::types::HPBUFFERARB;
+
+pub type WGLGETPBUFFERDCARBPROC =
+    unsafe extern "system" fn(hPbuffer: wgl_extra::types::HPBUFFERARB) -> wgl_extra::types::HDC;
+
+pub type WGLRELEASEPBUFFERDCARBPROC = unsafe extern "system" fn(
+    hPbuffer: wgl_extra::types::HPBUFFERARB,
+    hDC: wgl_extra::types::HDC,
+) -> std::os::raw::c_int;
+
+pub type WGLDESTROYPBUFFERARBPROC =
+    unsafe extern "system" fn(hPbuffer: wgl_extra::types::HPBUFFERARB) -> wgl_extra::types::BOOL;
+
+pub type WGLQUERYPBUFFERARBPROC = unsafe extern "system" fn(
+    hPbuffer: wgl_extra::types::HPBUFFERARB,
+    iAttribute: std::os::raw::c_int,
+    piValue: *mut std::os::raw::c_int,
+) -> wgl_extra::types::BOOL;
+
 pub struct WGLARBFunctions {
-    pub wglCreateContextAttribsARB: WGLCREATECONTEXTATTRIBSARBPROC,
-    pub wglSwapIntervalEXT: WGLSWAPINTERVALEXTPROC
+    pub wglCreateContextAttribsARB: Option<WGLCREATECONTEXTATTRIBSARBPROC>,
+    pub wglSwapIntervalEXT: WGLSWAPINTERVALEXTPROC,
+    pub wglGetExtensionsStringARB: Option<WGLGETEXTENSIONSSTRINGARBPROC>,
+    pub wglChoosePixelFormatARB: Option<WGLCHOOSEPIXELFORMATARBPROC>,
+    pub wglGetPixelFormatAttribivARB: Option<WGLGETPIXELFORMATATTRIBIVARBPROC>,
+    pub wglCreatePbufferARB: Option<WGLCREATEPBUFFERARBPROC>,
+    pub wglGetPbufferDCARB: Option<WGLGETPBUFFERDCARBPROC>,
+    pub wglReleasePbufferDCARB: Option<WGLRELEASEPBUFFERDCARBPROC>,
+    pub wglDestroyPbufferARB: Option<WGLDESTROYPBUFFERARBPROC>,
+    pub wglQueryPbufferARB: Option<WGLQUERYPBUFFERARBPROC>,
 }
 
 impl WGLARBFunctions {
     pub fn load() -> Self {
-        let wglCreateContextAttribsARB: WGLCREATECONTEXTATTRIBSARBPROC = unsafe {
+        let wglCreateContextAttribsARB: Option<WGLCREATECONTEXTATTRIBSARBPROC> = unsafe {
             transmute(wgl::GetProcAddress(
                 "wglCreateContextAttribsARB\0".as_ptr() as wgl::types::LPCSTR
             ))
@@ -34,9 +88,120 @@ impl WGLARBFunctions {
                 "wglSwapIntervalEXT\0".as_ptr() as wgl::types::LPCSTR
             ))
         };
+        let wglGetExtensionsStringARB: Option<WGLGETEXTENSIONSSTRINGARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglGetExtensionsStringARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
+        let wglChoosePixelFormatARB: Option<WGLCHOOSEPIXELFORMATARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglChoosePixelFormatARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
+        let wglGetPixelFormatAttribivARB: Option<WGLGETPIXELFORMATATTRIBIVARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglGetPixelFormatAttribivARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
+        let wglCreatePbufferARB: Option<WGLCREATEPBUFFERARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglCreatePbufferARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
+        let wglGetPbufferDCARB: Option<WGLGETPBUFFERDCARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglGetPbufferDCARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
+        let wglReleasePbufferDCARB: Option<WGLRELEASEPBUFFERDCARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglReleasePbufferDCARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
+        let wglDestroyPbufferARB: Option<WGLDESTROYPBUFFERARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglDestroyPbufferARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
+        let wglQueryPbufferARB: Option<WGLQUERYPBUFFERARBPROC> = unsafe {
+            transmute(wgl::GetProcAddress(
+                "wglQueryPbufferARB\0".as_ptr() as wgl::types::LPCSTR
+            ))
+        };
         Self {
             wglCreateContextAttribsARB,
             wglSwapIntervalEXT,
+            wglGetExtensionsStringARB,
+            wglChoosePixelFormatARB,
+            wglGetPixelFormatAttribivARB,
+            wglCreatePbufferARB,
+            wglGetPbufferDCARB,
+            wglReleasePbufferDCARB,
+            wglDestroyPbufferARB,
+            wglQueryPbufferARB,
+        }
+    }
+
+    pub fn is_extension_supported(&self, hdc: wgl_extra::types::HDC, name: &str) -> bool {
+        let f = match self.wglGetExtensionsStringARB {
+            Some(f) => f,
+            None => return false,
+        };
+        unsafe {
+            let extensions = f(hdc);
+            if extensions.is_null() {
+                return false;
+            }
+            std::ffi::CStr::from_ptr(extensions)
+                .to_string_lossy()
+                .split_whitespace()
+                .any(|e| e == name)
         }
     }
 }
+
+/// The parts of imm32 used for input method support, which winapi only partly binds.
+#[allow(non_camel_case_types, non_snake_case)]
+pub mod imm {
+    use winapi::shared::minwindef::{BOOL, DWORD};
+    use winapi::shared::windef::{HWND, POINT, RECT};
+    use std::os::raw::{c_long, c_void};
+
+    pub type HIMC = *mut c_void;
+
+    pub const GCS_COMPSTR: DWORD = 0x0008;
+    pub const GCS_CURSORPOS: DWORD = 0x0080;
+    pub const GCS_RESULTSTR: DWORD = 0x0800;
+
+    pub const CFS_POINT: DWORD = 0x0002;
+    pub const CFS_CANDIDATEPOS: DWORD = 0x0040;
+
+    pub const IACE_DEFAULT: DWORD = 0x0010;
+
+    pub const ISC_SHOWUICOMPOSITIONWINDOW: u32 = 0x8000_0000;
+
+    #[repr(C)]
+    pub struct COMPOSITIONFORM {
+        pub dwStyle: DWORD,
+        pub ptCurrentPos: POINT,
+        pub rcArea: RECT,
+    }
+
+    #[repr(C)]
+    pub struct CANDIDATEFORM {
+        pub dwIndex: DWORD,
+        pub dwStyle: DWORD,
+        pub ptCurrentPos: POINT,
+        pub rcArea: RECT,
+    }
+
+    #[link(name = "imm32")]
+    extern "system" {
+        pub fn ImmGetContext(hwnd: HWND) -> HIMC;
+        pub fn ImmReleaseContext(hwnd: HWND, himc: HIMC) -> BOOL;
+        pub fn ImmAssociateContextEx(hwnd: HWND, himc: HIMC, flags: DWORD) -> BOOL;
+        pub fn ImmGetCompositionStringW(himc: HIMC, index: DWORD, buffer: *mut c_void, length: DWORD) -> c_long;
+        pub fn ImmSetCompositionWindow(himc: HIMC, form: *mut COMPOSITIONFORM) -> BOOL;
+        pub fn ImmSetCandidateWindow(himc: HIMC, form: *mut CANDIDATEFORM) -> BOOL;
+    }
+}
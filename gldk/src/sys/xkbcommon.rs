@@ -0,0 +1,91 @@
+//! Bindings to the parts of libxkbcommon that turn `wl_keyboard` keys into keysyms and text.
+#![allow(non_camel_case_types)]
+
+use std::os::raw::{c_char, c_int};
+
+pub enum xkb_context {}
+pub enum xkb_keymap {}
+pub enum xkb_state {}
+pub enum xkb_compose_table {}
+pub enum xkb_compose_state {}
+
+pub type xkb_keycode_t = u32;
+pub type xkb_keysym_t = u32;
+pub type xkb_layout_index_t = u32;
+pub type xkb_level_index_t = u32;
+pub type xkb_mod_mask_t = u32;
+
+pub const XKB_CONTEXT_NO_FLAGS: c_int = 0;
+pub const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+pub const XKB_KEYMAP_COMPILE_NO_FLAGS: c_int = 0;
+pub const XKB_STATE_MODS_EFFECTIVE: c_int = 1 << 3;
+pub const XKB_COMPOSE_COMPILE_NO_FLAGS: c_int = 0;
+pub const XKB_COMPOSE_STATE_NO_FLAGS: c_int = 0;
+
+// enum xkb_compose_feed_result
+pub const XKB_COMPOSE_FEED_ACCEPTED: c_int = 1;
+
+// enum xkb_compose_status
+pub const XKB_COMPOSE_COMPOSING: c_int = 1;
+pub const XKB_COMPOSE_COMPOSED: c_int = 2;
+pub const XKB_COMPOSE_CANCELLED: c_int = 3;
+
+// Modifier names, with the usual bindings of Alt, NumLock and the Windows key.
+pub const XKB_MOD_NAME_SHIFT: &[u8] = b"Shift\0";
+pub const XKB_MOD_NAME_CAPS: &[u8] = b"Lock\0";
+pub const XKB_MOD_NAME_CTRL: &[u8] = b"Control\0";
+pub const XKB_MOD_NAME_ALT: &[u8] = b"Mod1\0";
+pub const XKB_MOD_NAME_NUM: &[u8] = b"Mod2\0";
+pub const XKB_MOD_NAME_LOGO: &[u8] = b"Mod4\0";
+
+#[link(name = "xkbcommon")]
+extern "C" {
+    pub fn xkb_context_new(flags: c_int) -> *mut xkb_context;
+    pub fn xkb_context_unref(context: *mut xkb_context);
+
+    pub fn xkb_keymap_new_from_string(
+        context: *mut xkb_context,
+        string: *const c_char,
+        format: c_int,
+        flags: c_int,
+    ) -> *mut xkb_keymap;
+    pub fn xkb_keymap_unref(keymap: *mut xkb_keymap);
+    pub fn xkb_keymap_key_repeats(keymap: *mut xkb_keymap, key: xkb_keycode_t) -> c_int;
+    pub fn xkb_keymap_key_get_syms_by_level(
+        keymap: *mut xkb_keymap,
+        key: xkb_keycode_t,
+        layout: xkb_layout_index_t,
+        level: xkb_level_index_t,
+        syms: *mut *const xkb_keysym_t,
+    ) -> c_int;
+
+    pub fn xkb_state_new(keymap: *mut xkb_keymap) -> *mut xkb_state;
+    pub fn xkb_state_unref(state: *mut xkb_state);
+    pub fn xkb_state_update_mask(
+        state: *mut xkb_state,
+        depressed_mods: xkb_mod_mask_t,
+        latched_mods: xkb_mod_mask_t,
+        locked_mods: xkb_mod_mask_t,
+        depressed_layout: xkb_layout_index_t,
+        latched_layout: xkb_layout_index_t,
+        locked_layout: xkb_layout_index_t,
+    ) -> c_int;
+    pub fn xkb_state_key_get_layout(state: *mut xkb_state, key: xkb_keycode_t) -> xkb_layout_index_t;
+    pub fn xkb_state_key_get_one_sym(state: *mut xkb_state, key: xkb_keycode_t) -> xkb_keysym_t;
+    pub fn xkb_state_key_get_utf8(state: *mut xkb_state, key: xkb_keycode_t, buffer: *mut c_char, size: usize)
+        -> c_int;
+    pub fn xkb_state_mod_name_is_active(state: *mut xkb_state, name: *const c_char, kind: c_int) -> c_int;
+
+    pub fn xkb_compose_table_new_from_locale(
+        context: *mut xkb_context,
+        locale: *const c_char,
+        flags: c_int,
+    ) -> *mut xkb_compose_table;
+    pub fn xkb_compose_table_unref(table: *mut xkb_compose_table);
+    pub fn xkb_compose_state_new(table: *mut xkb_compose_table, flags: c_int) -> *mut xkb_compose_state;
+    pub fn xkb_compose_state_unref(state: *mut xkb_compose_state);
+    pub fn xkb_compose_state_feed(state: *mut xkb_compose_state, keysym: xkb_keysym_t) -> c_int;
+    pub fn xkb_compose_state_reset(state: *mut xkb_compose_state);
+    pub fn xkb_compose_state_get_status(state: *mut xkb_compose_state) -> c_int;
+    pub fn xkb_compose_state_get_utf8(state: *mut xkb_compose_state, buffer: *mut c_char, size: usize) -> c_int;
+}
@@ -1,50 +1,436 @@
+use crate::context::ContextInfo;
 use crate::platform_impl::window::RWindow;
-use crate::GLConfig;
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use crate::{GLConfig, GLFramebufferFormat, GLVersion};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle};
+use std::collections::HashMap;
 use std::ffi::c_void;
+use std::time::{Duration, Instant};
 use crate::error::GLDKError;
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct ControlFlow {}
+/// What the event loop does once the callback returns. It keeps its value from one event to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControlFlow {
+    /// Don't wait for events: whenever there are none left, windows get `RedrawRequested`, so
+    /// they can be rendered continuously.
+    Poll,
+    /// Sleep until the next event.
+    #[default]
+    Wait,
+    /// Sleep until the next event or the deadline, at which windows get `RedrawRequested`. The
+    /// callback usually sets the next deadline then, a deadline in the past behaves like `Poll`.
+    WaitUntil(Instant),
+    /// Stop the loop, which returns the code.
+    Exit(i32),
+}
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+impl ControlFlow {
+    /// How long the loop may block waiting for an event, `None` for as long as it takes.
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        match *self {
+            ControlFlow::Poll | ControlFlow::Exit(_) => Some(Duration::ZERO),
+            ControlFlow::Wait => None,
+            ControlFlow::WaitUntil(deadline) => Some(deadline.saturating_duration_since(Instant::now())),
+        }
+    }
+
+    /// Whether waiting without getting an event is a reason to send `RedrawRequested`.
+    pub(crate) fn is_due(&self) -> bool {
+        match *self {
+            ControlFlow::Poll => true,
+            ControlFlow::WaitUntil(deadline) => Instant::now() >= deadline,
+            ControlFlow::Wait | ControlFlow::Exit(_) => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum WindowEvent {
     RedrawRequested,
 
-    Keyup(KeyCode),
-    Keydown(KeyCode),
+    Keyup(KeyboardInput),
+    Keydown(KeyboardInput),
+    /// Text typed into the window, once the layout, dead keys and input method have been applied.
+    /// Follows the `Keydown` that produced it, if any.
+    ReceivedCharacter(char),
+    /// The text being composed by the input method changed. `cursor` is the byte offset of the caret
+    /// in `text`, `None` when the input method hides it. An empty `text` ends the composition.
+    ImePreedit { text: String, cursor: Option<usize> },
+    /// Text the input method committed, which is not also sent as `ReceivedCharacter`.
+    ImeCommit(String),
+
+    /// The cursor moved over the window, relative to the top-left corner of its client area.
+    CursorMoved {
+        physical: Position,
+        /// `physical` divided by the window's scale factor.
+        logical: Position,
+    },
+    CursorEntered,
+    CursorLeft,
+    MouseButtonDown(MouseButton),
+    MouseButtonUp(MouseButton),
+    MouseWheel(ScrollDelta),
+
+    /// The new size of the client area, in physical pixels.
+    Resized(u32, u32),
+    /// The new position of the client area on screen. Never sent on Wayland.
+    Moved(i32, i32),
+    /// Whether the window now has keyboard focus.
+    Focused(bool),
+    Minimized,
+    /// The window was brought back from being minimized.
+    Restored,
 
     CloseRequested,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Position {
+    pub(crate) fn to_logical(self, scale_factor: f64) -> Position {
+        Position {
+            x: self.x / scale_factor,
+            y: self.y / scale_factor,
+        }
+    }
+}
+
+/// Positive `y` scrolls up, away from the user, and positive `x` scrolls right.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct KeyCode(pub u32);
+pub enum ScrollDelta {
+    /// Wheel notches. High-resolution wheels and touchpads report fractions of a notch on Windows,
+    /// and on X11 servers with XInput 2.1, the others only report whole notches.
+    Lines { x: f32, y: f32 },
+    /// Physical pixels, from touchpads on Wayland.
+    Pixels { x: f64, y: f64 },
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// The first extra button, usually bound to "back".
+    Back,
+    /// The second extra button, usually bound to "forward".
+    Forward,
+    /// Any further button, numbered as the platform numbers it.
+    Other(u16),
+}
 
-impl Into<char> for KeyCode {
-    fn into(self) -> char {
-        std::char::from_u32(self.0).unwrap()
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyboardInput {
+    /// What the key means in the current keyboard layout.
+    pub keycode: VirtualKeyCode,
+    /// Identifies the physical key whatever the layout: the Win32 scancode, with 0xE000 added for
+    /// extended keys, or the X11 keycode, which on Wayland is likewise the evdev code plus 8.
+    pub scancode: u32,
+    pub modifiers: Modifiers,
+    /// Whether this `Keydown` comes from the key being held rather than pressed. Always false for
+    /// `Keyup`, and on X11 servers without detectable auto-repeat.
+    pub repeat: bool,
+}
+
+/// The keys held down in a window and its modifiers, see `GLDKWindow::keyboard_state`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyboardState {
+    /// Keyed by scancode, so keys without a name are tracked too.
+    pressed: HashMap<u32, VirtualKeyCode>,
+    modifiers: Modifiers,
+}
+
+impl KeyboardState {
+    pub fn is_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.pressed.values().any(|&pressed| pressed == key)
+    }
+
+    pub fn is_scancode_pressed(&self, scancode: u32) -> bool {
+        self.pressed.contains_key(&scancode)
+    }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = VirtualKeyCode> + '_ {
+        self.pressed.values().copied()
+    }
+
+    /// Shift, Ctrl, Alt and Super follow the keys held, the locks are as of the last key event.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Records a `Keydown`, returning whether the key was already held.
+    pub(crate) fn press(&mut self, input: &KeyboardInput) -> bool {
+        let repeat = self.pressed.insert(input.scancode, input.keycode).is_some();
+        self.update_modifiers(input.modifiers);
+        repeat
+    }
+
+    pub(crate) fn release(&mut self, input: &KeyboardInput) {
+        self.pressed.remove(&input.scancode);
+        self.update_modifiers(input.modifiers);
+    }
+
+    /// Keys released while the window didn't have focus are never reported, so everything is
+    /// considered released when it loses it.
+    pub(crate) fn clear(&mut self) {
+        self.pressed.clear();
+        self.update_modifiers(self.modifiers);
+    }
+
+    fn update_modifiers(&mut self, reported: Modifiers) {
+        use VirtualKeyCode::*;
+        let held = |left, right| self.is_pressed(left) || self.is_pressed(right);
+        self.modifiers = Modifiers {
+            shift: held(LShift, RShift),
+            ctrl: held(LControl, RControl),
+            alt: held(LAlt, RAlt),
+            super_key: held(LSuper, RSuper),
+            ..reported
+        };
     }
 }
 
+/// Which modifier keys were held, and which locks were on, when the event happened.
 #[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows or Command key.
+    pub super_key: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+}
+
+/// A key named after what it produces on a US layout, except for the numpad and the modifiers which
+/// are named after their position.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VirtualKeyCode {
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+
+    Escape,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+
+    Left,
+    Up,
+    Right,
+    Down,
+
+    Backspace,
+    Return,
+    Space,
+    Tab,
+    CapsLock,
+    Menu,
+
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    NumLock,
+
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    LSuper,
+    RSuper,
+
+    Grave,
+    Minus,
+    Equals,
+    LBracket,
+    RBracket,
+    Backslash,
+    Semicolon,
+    Apostrophe,
+    Comma,
+    Period,
+    Slash,
+
+    /// A key GLDK has no name for, `KeyboardInput::scancode` still tells which one it is.
+    Unknown,
+}
+
+impl VirtualKeyCode {
+    const DIGITS: [VirtualKeyCode; 10] = {
+        use VirtualKeyCode::*;
+        [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9]
+    };
+
+    const LETTERS: [VirtualKeyCode; 26] = {
+        use VirtualKeyCode::*;
+        [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z]
+    };
+
+    const FUNCTION_KEYS: [VirtualKeyCode; 24] = {
+        use VirtualKeyCode::*;
+        [
+            F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20,
+            F21, F22, F23, F24,
+        ]
+    };
+
+    const NUMPAD_DIGITS: [VirtualKeyCode; 10] = {
+        use VirtualKeyCode::*;
+        [Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9]
+    };
+
+    /// `Key0` for 0 up to `Key9` for 9.
+    pub(crate) fn digit(index: u32) -> Self {
+        Self::DIGITS.get(index as usize).copied().unwrap_or(Self::Unknown)
+    }
+
+    /// `A` for 0 up to `Z` for 25.
+    pub(crate) fn letter(index: u32) -> Self {
+        Self::LETTERS.get(index as usize).copied().unwrap_or(Self::Unknown)
+    }
+
+    /// `F1` for 0 up to `F24` for 23.
+    pub(crate) fn function_key(index: u32) -> Self {
+        Self::FUNCTION_KEYS.get(index as usize).copied().unwrap_or(Self::Unknown)
+    }
+
+    /// `Numpad0` for 0 up to `Numpad9` for 9.
+    pub(crate) fn numpad_digit(index: u32) -> Self {
+        Self::NUMPAD_DIGITS.get(index as usize).copied().unwrap_or(Self::Unknown)
+    }
+}
+
+/// Identifies a window for as long as it is alive: the HWND on Windows, the XID on X11 and the
+/// `wl_surface` on Wayland.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct WindowID(pub u64);
 
 #[repr(C)]
 pub struct GLDKWindow {
-    inner: RWindow,
+    pub(crate) inner: RWindow,
 }
 
 impl GLDKWindow {
     pub fn new(width: u32, height: u32, title: &str, conf: Option<GLConfig>) -> Result<Self,GLDKError> {
+        Self::create(width, height, title, conf, None)
+    }
+
+    /// Creates a window whose context shares textures, buffers and other objects with `share`'s context.
+    pub fn new_shared(
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: Option<GLConfig>,
+        share: &GLDKWindow,
+    ) -> Result<Self, GLDKError> {
+        Self::create(width, height, title, conf, Some(share))
+    }
+
+    fn create(
+        width: u32,
+        height: u32,
+        title: &str,
+        conf: Option<GLConfig>,
+        share: Option<&GLDKWindow>,
+    ) -> Result<Self, GLDKError> {
         let conf = match conf {
             None => GLConfig::default(),
             Some(c) => c,
         };
 
-        let inner = match RWindow::new(width, height, title, conf) {
+        conf.validate()?;
+
+        let inner = match RWindow::new(width, height, title, conf, share.map(|s| &s.inner)) {
             Ok(i) => i,
             Err(e) => return Err(e)
         };
@@ -58,15 +444,41 @@ impl GLDKWindow {
         self.inner.id()
     }
 
+    /// Resolves both desktop OpenGL and OpenGL ES entry points, depending on the context that was created.
     pub fn get_proc_address(&self, addr: &str) -> *const c_void {
         self.inner.get_proc_address(addr)
     }
 
-    pub fn run<F>(&self, callback: F)
+    /// Returns the version that was actually created, see `GLConfig::version_policy`.
+    pub fn get_gl_version(&self) -> GLVersion {
+        self.inner.get_gl_version()
+    }
+
+    /// Returns what the driver actually created: version, profile, flags, renderer strings and extensions.
+    pub fn get_context_info(&self) -> &ContextInfo {
+        self.inner.get_context_info()
+    }
+
+    /// The ratio of physical to logical pixels, from the DPI the platform reports, or on Wayland the
+    /// largest scale of the outputs the window is on.
+    pub fn scale_factor(&self) -> f64 {
+        self.inner.scale_factor()
+    }
+
+    /// Returns the format of the default framebuffer actually chosen by the driver,
+    /// which may differ from the one requested in `GLConfig`.
+    pub fn get_framebuffer_format(&self) -> GLFramebufferFormat {
+        self.inner.get_framebuffer_format()
+    }
+
+    /// Runs this window's event loop until the callback sets `ControlFlow::Exit`, returning its code,
+    /// or until `quit` is called, returning 0. Returns 1 if the connection to the display server is
+    /// lost. Windows owned by a `GLDKEventLoop` are driven by `GLDKEventLoop::run` instead.
+    pub fn run<F>(&self, callback: F) -> i32
     where
-        F: FnMut(WindowEvent),
+        F: FnMut(WindowEvent, &mut ControlFlow),
     {
-        self.inner.run(callback);
+        self.inner.run(callback)
     }
 
     // GL Functions
@@ -85,10 +497,12 @@ impl GLDKWindow {
 
     // Common
 
+    /// Does nothing on Wayland, where windows are shown by their first `swap_buffers`.
     pub fn show(&self) {
         self.inner.show();
     }
 
+    /// Does nothing on Wayland, which has no way to hide a window short of destroying it.
     pub fn hide(&self) {
         self.inner.hide();
     }
@@ -97,6 +511,7 @@ impl GLDKWindow {
         self.inner.set_window_title(title);
     }
 
+    /// Does nothing on Wayland, where the compositor draws the borders, if any.
     pub fn set_window_border_width(&self, width: u32) {
         self.inner.set_window_border_width(width);
     }
@@ -105,10 +520,12 @@ impl GLDKWindow {
         self.inner.get_window_size()
     }
 
+    /// Always `(0, 0)` on Wayland, which doesn't tell clients where their windows are.
     pub fn get_window_pos(&self) -> (u32, u32) {
         self.inner.get_window_pos()
     }
 
+    /// Does nothing on Wayland, where decorations are up to the compositor.
     pub fn set_undecorated(&self,b: bool) {
         self.inner.set_undecorated(b);
     }
@@ -121,6 +538,23 @@ impl GLDKWindow {
         self.inner.set_minimized(minimized);
     }
 
+    /// A snapshot of the keys held down in this window, as of the last event it received.
+    pub fn keyboard_state(&self) -> KeyboardState {
+        self.inner.keyboard_state()
+    }
+
+    /// Lets the input method compose text for this window, which it does by default.
+    /// Does nothing on Wayland.
+    pub fn set_ime_enabled(&self, enabled: bool) {
+        self.inner.set_ime_enabled(enabled);
+    }
+
+    /// Moves the input method's candidate window next to `(x, y)`, in physical pixels relative to the
+    /// top-left corner of the client area, usually where the text cursor is. Does nothing on Wayland.
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.inner.set_ime_position(x, y);
+    }
+
     pub fn quit(&self) {
         self.inner.quit();
     }
@@ -131,3 +565,9 @@ unsafe impl HasRawWindowHandle for GLDKWindow {
         self.inner.raw_window_handle()
     }
 }
+
+unsafe impl HasRawDisplayHandle for GLDKWindow {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.inner.raw_display_handle()
+    }
+}
@@ -0,0 +1,125 @@
+//! Covers the `WGL_ARB_pbuffer` path on Windows and EGL's surfaceless platform on Linux, where
+//! Mesa's llvmpipe is enough.
+
+use gldk::headless::GLDKHeadlessContext;
+use gldk::{GLConfig, GLContextBackend, GLProfile, GLVersion};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+
+fn clear_and_read(context: &GLDKHeadlessContext, color: [f32; 4]) -> Vec<u8> {
+    context.make_current();
+    gl::load_with(|s| context.get_proc_address(s));
+
+    let mut pixels = vec![0u8; (WIDTH * HEIGHT * 4) as usize];
+    unsafe {
+        gl::Viewport(0, 0, WIDTH as i32, HEIGHT as i32);
+        gl::ClearColor(color[0], color[1], color[2], color[3]);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::ReadPixels(
+            0,
+            0,
+            WIDTH as i32,
+            HEIGHT as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+        assert_eq!(gl::GetError(), gl::NO_ERROR);
+    }
+    pixels
+}
+
+#[test]
+fn headless_core_context_clears() {
+    let context = GLDKHeadlessContext::new(
+        WIDTH,
+        HEIGHT,
+        Some(GLConfig {
+            // WGL on Windows, still EGL on Linux.
+            backend: GLContextBackend::Native,
+            version: GLVersion::V3_3,
+            profile: Some(GLProfile::Core),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(context.get_gl_version(), GLVersion::V3_3);
+    assert_eq!(context.get_framebuffer_size(), (WIDTH, HEIGHT));
+    let format = context.get_framebuffer_format();
+    assert!(!format.double_buffer);
+    assert!(format.red_bits >= 8 && format.green_bits >= 8 && format.blue_bits >= 8);
+
+    let pixels = clear_and_read(&context, [1.0, 0.0, 1.0, 1.0]);
+    for pixel in pixels.chunks(4) {
+        assert_eq!(pixel, [255, 0, 255, 255]);
+    }
+
+    let info = context.get_context_info();
+    assert!(matches!(
+        info.version,
+        GLVersion::V3_3
+            | GLVersion::V4_0
+            | GLVersion::V4_1
+            | GLVersion::V4_2
+            | GLVersion::V4_3
+            | GLVersion::V4_4
+            | GLVersion::V4_5
+            | GLVersion::V4_6
+    ));
+    assert_eq!(info.profile, Some(GLProfile::Core));
+    assert!(!info.flags.debug);
+    assert!(info.flags.robust_access.is_none());
+    assert!(!info.version_string.starts_with("OpenGL ES"));
+    assert!(!info.vendor.is_empty());
+    assert!(!info.renderer.is_empty());
+    assert!(!info.shading_language_version.is_empty());
+    assert!(!info.extensions.is_empty());
+}
+
+// WGL only creates OpenGL ES contexts through WGL_EXT_create_context_es2_profile, which few
+// desktop drivers have.
+#[cfg(not(windows))]
+#[test]
+fn headless_es_context_clears() {
+    let context = GLDKHeadlessContext::new(
+        WIDTH,
+        HEIGHT,
+        Some(GLConfig {
+            version: GLVersion::ES2_0,
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    let pixels = clear_and_read(&context, [0.0, 1.0, 0.0, 1.0]);
+    for pixel in pixels.chunks(4) {
+        assert_eq!(pixel, [0, 255, 0, 255]);
+    }
+
+    // Drivers hand out the newest compatible OpenGL ES version, which ContextInfo reports.
+    let info = context.get_context_info();
+    assert!(info.version.is_es());
+    assert!(info.version_string.starts_with("OpenGL ES"));
+    assert_eq!(info.profile, None);
+    assert!(!info.renderer.is_empty());
+    assert!(!info.extensions.is_empty());
+}
+
+#[cfg(windows)]
+#[test]
+fn headless_egl_is_unsupported_on_windows() {
+    let result = GLDKHeadlessContext::new(
+        WIDTH,
+        HEIGHT,
+        Some(GLConfig {
+            backend: GLContextBackend::Egl,
+            ..Default::default()
+        }),
+    );
+    assert!(matches!(
+        result.map(|_| ()).unwrap_err().kind(),
+        gldk::error::ErrorKind::Unsupported(_)
+    ));
+}
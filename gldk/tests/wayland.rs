@@ -0,0 +1,64 @@
+//! Runs against whatever compositor `WAYLAND_DISPLAY` names, headless weston with Mesa's llvmpipe on
+//! CI, and is skipped without one.
+#![cfg(all(target_os = "linux", feature = "wayland"))]
+
+use gldk::window::GLDKWindow;
+use gldk::{GLConfig, GLVersion};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+
+fn compositor_available() -> bool {
+    if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        eprintln!("WAYLAND_DISPLAY is not set, skipping");
+        return false;
+    }
+    std::env::set_var("GLDK_UNIX_BACKEND", "wayland");
+    true
+}
+
+#[test]
+fn wayland_window_clears() {
+    if !compositor_available() {
+        return;
+    }
+    let window = GLDKWindow::new(
+        WIDTH,
+        HEIGHT,
+        "GLDK Wayland test",
+        Some(GLConfig {
+            version: GLVersion::V3_3,
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    window.make_current();
+    gl::load_with(|s| window.get_proc_address(s));
+
+    assert_eq!(window.get_gl_version(), GLVersion::V3_3);
+    assert!(window.get_framebuffer_format().double_buffer);
+    // Until it is mapped the window is on no output, so it isn't scaled yet.
+    assert_eq!(window.scale_factor(), 1.0);
+    assert_eq!(window.get_window_size(), (WIDTH, HEIGHT));
+
+    let mut pixels = vec![0u8; (WIDTH * HEIGHT * 4) as usize];
+    unsafe {
+        gl::Viewport(0, 0, WIDTH as i32, HEIGHT as i32);
+        gl::ClearColor(1.0, 0.0, 0.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::ReadPixels(
+            0,
+            0,
+            WIDTH as i32,
+            HEIGHT as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+        assert_eq!(gl::GetError(), gl::NO_ERROR);
+    }
+    for pixel in pixels.chunks(4) {
+        assert_eq!(pixel, [255, 0, 0, 255]);
+    }
+    window.swap_buffers();
+}
@@ -0,0 +1,66 @@
+//! Runs against whatever X server `DISPLAY` names, Xvfb with Mesa's llvmpipe on CI, and is
+//! skipped without one.
+#![cfg(target_os = "linux")]
+
+use gldk::window::GLDKWindow;
+use gldk::{GLConfig, GLContextBackend, GLProfile, GLVersion};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+
+fn x_server_available() -> bool {
+    if std::env::var_os("DISPLAY").is_none() {
+        eprintln!("DISPLAY is not set, skipping");
+        return false;
+    }
+    std::env::set_var("GLDK_UNIX_BACKEND", "x11");
+    true
+}
+
+#[test]
+fn glx_window_clears() {
+    if !x_server_available() {
+        return;
+    }
+    let window = GLDKWindow::new(
+        WIDTH,
+        HEIGHT,
+        "GLDK GLX test",
+        Some(GLConfig {
+            backend: GLContextBackend::Native,
+            version: GLVersion::V3_3,
+            profile: Some(GLProfile::Core),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    window.show();
+    window.make_current();
+    gl::load_with(|s| window.get_proc_address(s));
+
+    assert_eq!(window.get_gl_version(), GLVersion::V3_3);
+    assert_eq!(window.get_context_info().profile, Some(GLProfile::Core));
+    assert!(window.get_framebuffer_format().double_buffer);
+
+    let mut pixels = vec![0u8; (WIDTH * HEIGHT * 4) as usize];
+    unsafe {
+        gl::Viewport(0, 0, WIDTH as i32, HEIGHT as i32);
+        gl::ClearColor(0.0, 0.0, 1.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        // The back buffer, which is what double-buffered contexts read from by default.
+        gl::ReadPixels(
+            0,
+            0,
+            WIDTH as i32,
+            HEIGHT as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+        assert_eq!(gl::GetError(), gl::NO_ERROR);
+    }
+    for pixel in pixels.chunks(4) {
+        assert_eq!(pixel, [0, 0, 255, 255]);
+    }
+    window.swap_buffers();
+}